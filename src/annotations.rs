@@ -0,0 +1,328 @@
+use crate::diagnostic::{Diagnostic, DiagnosticSpan};
+use crate::directory::Directory;
+use crate::path::CanonicalPath;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Expected diagnostics written inline in the test case source, in the style of
+// rustc's compiletest:
+//
+//     let x: u8 = "";  //~ ERROR mismatched types
+//     //~^ HELP consider
+//
+// `//~` refers to the line on which it appears, each `^` moves one line up, and
+// `//~|` refers to the same line as the previous annotation. Any of the files
+// of a directory test case can have annotations.
+pub(crate) struct Annotation {
+    pub file: PathBuf,
+    pub line: usize,
+    pub kind: Kind,
+    pub message: String,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) enum Kind {
+    Error,
+    Warning,
+    Help,
+    Note,
+}
+
+// The files of the test case, relative to the source directory like the test
+// case's path: the file itself, or every .rs file in the directory.
+pub(crate) fn source_files(source_dir: &Directory, path: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if source_dir.join(path).is_dir() {
+        add_dir(source_dir, path, &mut files);
+        files.sort();
+    } else {
+        files.push(path.to_owned());
+    }
+    files
+}
+
+fn add_dir(source_dir: &Directory, dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(source_dir.join(dir)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = dir.join(entry.file_name());
+        if entry.path().is_dir() {
+            add_dir(source_dir, &path, files);
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+pub(crate) fn parse(file: &Path, source: &str) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    let mut previous_line = None;
+
+    for (i, line) in source.lines().enumerate() {
+        let Some(start) = line.find("//~") else {
+            continue;
+        };
+        let mut rest = &line[start + 3..];
+        let line_number = if let Some(after_pipe) = rest.strip_prefix('|') {
+            rest = after_pipe;
+            match previous_line {
+                Some(previous_line) => previous_line,
+                None => continue,
+            }
+        } else {
+            let carets = rest.bytes().take_while(|&b| b == b'^').count();
+            rest = &rest[carets..];
+            match (i + 1).checked_sub(carets) {
+                Some(line_number) if line_number > 0 => line_number,
+                _ => continue,
+            }
+        };
+
+        let rest = rest.trim_start();
+        let word_len = rest
+            .find(|ch: char| !ch.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let kind = match &rest[..word_len] {
+            "ERROR" => Kind::Error,
+            "WARN" | "WARNING" => Kind::Warning,
+            "HELP" => Kind::Help,
+            "NOTE" => Kind::Note,
+            _ => continue,
+        };
+        let message = rest[word_len..].trim_start_matches(':').trim().to_owned();
+
+        previous_line = Some(line_number);
+        annotations.push(Annotation {
+            file: file.to_owned(),
+            line: line_number,
+            kind,
+            message,
+        });
+    }
+
+    annotations
+}
+
+// A diagnostic emitted by the compiler, flattened so that child help and note
+// messages stand on their own.
+pub(crate) struct Actual {
+    pub location: Location,
+    pub kind: Kind,
+    pub message: String,
+}
+
+#[derive(Clone, PartialEq)]
+pub(crate) enum Location {
+    // A line in one of the test case's files, which an annotation can refer to.
+    Case(PathBuf, usize),
+    // A line elsewhere, such as in an aux crate, even after following macro
+    // expansions back to where the macro was invoked.
+    Outside(PathBuf, usize),
+}
+
+pub(crate) struct Mismatch {
+    pub not_found: Vec<Annotation>,
+    pub unexpected: Vec<Actual>,
+    // Errors that no annotation can refer to, which fail the test case all the
+    // same.
+    pub outside: Vec<Actual>,
+}
+
+pub(crate) fn check(
+    source_dir: &Directory,
+    files: &[PathBuf],
+    expected: Vec<Annotation>,
+    diagnostics: &[Diagnostic],
+) -> Result<(), Mismatch> {
+    let files: Vec<(CanonicalPath, &Path)> = files
+        .iter()
+        .map(|file| (CanonicalPath::new(&source_dir.join(file)), file.as_path()))
+        .collect();
+    let mut actual = Vec::new();
+    for diag in diagnostics {
+        flatten(&files, diag, None, &mut actual);
+    }
+
+    let annotated_kinds: Vec<Kind> = expected.iter().map(|annotation| annotation.kind).collect();
+    let mut not_found = Vec::new();
+    let mut matched = vec![false; actual.len()];
+    for annotation in expected {
+        let index = actual.iter().enumerate().position(|(i, actual)| {
+            !matched[i]
+                && actual.kind == annotation.kind
+                && actual.location == Location::Case(annotation.file.clone(), annotation.line)
+                && actual.message.contains(&annotation.message)
+        });
+        match index {
+            Some(i) => matched[i] = true,
+            None => not_found.push(annotation),
+        }
+    }
+
+    // Errors must always be annotated. Other kinds of diagnostic are only
+    // checked exhaustively if the test case has annotations of that kind.
+    let mut unexpected = Vec::new();
+    let mut outside = Vec::new();
+    for (actual, matched) in actual.into_iter().zip(matched) {
+        if matched {
+            continue;
+        }
+        if let Location::Case(..) = actual.location {
+            if actual.kind == Kind::Error || annotated_kinds.contains(&actual.kind) {
+                unexpected.push(actual);
+            }
+        } else if actual.kind == Kind::Error {
+            outside.push(actual);
+        }
+    }
+
+    if not_found.is_empty() && unexpected.is_empty() && outside.is_empty() {
+        Ok(())
+    } else {
+        Err(Mismatch {
+            not_found,
+            unexpected,
+            outside,
+        })
+    }
+}
+
+fn flatten(
+    files: &[(CanonicalPath, &Path)],
+    diag: &Diagnostic,
+    parent: Option<&Location>,
+    actual: &mut Vec<Actual>,
+) {
+    let kind = match diag.level.as_str() {
+        "error" | "error: internal compiler error" => Kind::Error,
+        "warning" => Kind::Warning,
+        "help" => Kind::Help,
+        "note" => Kind::Note,
+        _ => return,
+    };
+
+    let location = match (diag.primary_span(), parent) {
+        (Some(span), _) => locate(files, span),
+        (None, Some(parent_location)) => parent_location.clone(),
+        // Summary messages like "aborting due to 2 previous errors" have no
+        // span and carry no information of their own.
+        (None, None) => return,
+    };
+
+    actual.push(Actual {
+        location: location.clone(),
+        kind,
+        message: diag.message.clone(),
+    });
+
+    for child in &diag.children {
+        flatten(files, child, Some(&location), actual);
+    }
+}
+
+fn locate(files: &[(CanonicalPath, &Path)], span: &DiagnosticSpan) -> Location {
+    let mut call_site = span;
+    loop {
+        let canonical = CanonicalPath::new(&call_site.file_name);
+        if let Some((_, file)) = files.iter().find(|(path, _)| *path == canonical) {
+            return Location::Case(file.to_path_buf(), call_site.line_start);
+        }
+        match &call_site.expansion {
+            Some(expansion) => call_site = &expansion.span,
+            None => return Location::Outside(span.file_name.clone(), span.line_start),
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let (Location::Case(file, line) | Location::Outside(file, line)) = self;
+        write!(formatter, "{}:{}", file.display(), line)
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(match self {
+            Kind::Error => "ERROR",
+            Kind::Warning => "WARN",
+            Kind::Help => "HELP",
+            Kind::Note => "NOTE",
+        })
+    }
+}
+
+#[test]
+fn test_parse() {
+    let source = "\
+        fn main() {\n\
+            let x: u8 = \"\"; //~ ERROR mismatched types\n\
+            //~^ HELP: consider\n\
+            //~| NOTE expected\n\
+        }\n\
+    ";
+    let annotations = parse(Path::new("tests/ui/annotated.rs"), source);
+    let parsed: Vec<_> = annotations
+        .iter()
+        .map(|a| (a.line, a.kind.to_string(), a.message.as_str()))
+        .collect();
+    assert_eq!(
+        parsed,
+        [
+            (2, "ERROR".to_owned(), "mismatched types"),
+            (2, "HELP".to_owned(), "consider"),
+            (2, "NOTE".to_owned(), "expected"),
+        ],
+    );
+}
+
+#[test]
+fn test_check_outside() {
+    let file = PathBuf::from("tests/ui/compile-fail-annotated.rs");
+    let span = |file_name: &str, line: usize, expansion: Option<serde_json::Value>| {
+        serde_json::json!({
+            "file_name": file_name,
+            "byte_start": 0,
+            "byte_end": 0,
+            "line_start": line,
+            "column_start": 1,
+            "is_primary": true,
+            "expansion": expansion.map(|span| serde_json::json!({ "span": span })),
+        })
+    };
+    let error = |span| {
+        serde_json::from_value::<Diagnostic>(serde_json::json!({
+            "message": "mismatched types",
+            "level": "error",
+            "spans": [span],
+            "children": [],
+        }))
+        .unwrap()
+    };
+    let diagnostics = [
+        // Inside a macro from an aux crate, invoked by the test case.
+        error(span(
+            "tests/ui/aux/helper.rs",
+            3,
+            Some(span(&file.to_string_lossy(), 2, None)),
+        )),
+        error(span("tests/ui/aux/helper.rs", 7, None)),
+    ];
+    let expected = parse(
+        &file,
+        "fn main() {\n    f!(); //~ ERROR mismatched types\n}\n",
+    );
+
+    let source_dir = Directory::current().unwrap();
+    let mismatch = check(&source_dir, &[file], expected, &diagnostics).unwrap_err();
+    assert!(mismatch.not_found.is_empty());
+    assert!(mismatch.unexpected.is_empty());
+    let outside: Vec<String> = mismatch
+        .outside
+        .iter()
+        .map(|actual| actual.location.to_string())
+        .collect();
+    assert_eq!(outside, ["tests/ui/aux/helper.rs:7"]);
+}
@@ -0,0 +1,363 @@
+use crate::directory::Directory;
+use crate::env::Update;
+use crate::manifest::Name;
+use crate::normalize::Custom;
+use crate::orphans::SNAPSHOT_EXTENSIONS;
+use crate::report::{Outcome, TestReport};
+use crate::run::Project;
+use crate::{cargo, rustc, rustflags, Expected, Test};
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap as Map;
+use std::env;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use target_triple::TARGET;
+
+// Test cases that passed in a previous run, keyed by a hash of everything that
+// could affect their outcome: the files the compiler read while building the
+// test case, the test case's snapshots, the generated manifest, the compiler
+// version and flags, the enabled features, and the fingerprints of the crate
+// under test and its dependencies.
+//
+// Which files the compiler read comes from the dep-info file rustc writes next
+// to the test case's build output, so this covers modules, `#[path]` and
+// `include!` too. Test cases that get run are never cached, as there is no
+// telling what their executable depends on.
+#[derive(Debug)]
+pub(crate) struct Cache {
+    path: PathBuf,
+    source_dir: Directory,
+    base: u64,
+    entries: Map<String, Entry>,
+    // Dependencies of the test cases built during this run, by bin name.
+    built: Mutex<Map<String, Deps>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Entry {
+    key: String,
+    deps: Deps,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct Deps {
+    files: Vec<PathBuf>,
+    env: Vec<(String, Option<String>)>,
+}
+
+impl Cache {
+    pub fn load(project: &Project) -> Option<Self> {
+        let base = base(project)?;
+
+        let path = path!(project.dir / "cache.json");
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default();
+
+        Some(Cache {
+            path,
+            source_dir: project.source_dir.clone(),
+            base,
+            entries,
+            built: Mutex::new(Map::new()),
+        })
+    }
+
+    pub fn is_fresh(&self, test: &Test) -> bool {
+        if !cacheable(test.expected) {
+            return false;
+        }
+        let Some(entry) = self.entries.get(&*test.path.to_string_lossy()) else {
+            return false;
+        };
+        let env_unchanged = entry
+            .deps
+            .env
+            .iter()
+            .all(|(var, value)| env::var(var).ok() == *value);
+        env_unchanged && self.key(test, &entry.deps).as_ref() == Some(&entry.key)
+    }
+
+    pub fn record_dep_info(&self, project: &Project, tests: &[(&Name, &Test)]) {
+        let mut built = self.built.lock().unwrap_or_else(PoisonError::into_inner);
+        for (name, test) in tests {
+            if let Some(deps) = dep_info(project, name, test) {
+                built.insert(name.0.clone(), deps);
+            }
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        project: &Project,
+        reports: &[TestReport],
+        tests: &Map<PathBuf, (Name, Test)>,
+    ) {
+        let mut built = mem::take(self.built.get_mut().unwrap_or_else(PoisonError::into_inner));
+
+        // Building the test cases rebuilds the crate under test with different
+        // flags, which touches its fingerprints after this cache was loaded.
+        match base(project) {
+            Some(base) => self.base = base,
+            None => return,
+        }
+
+        for report in reports {
+            let path = report.path.to_string_lossy().into_owned();
+            let Some((name, test)) = tests.get(&report.path) else {
+                self.entries.remove(&path);
+                continue;
+            };
+            let deps = match report.outcome {
+                // Test cases reported as cached were not built this time, so
+                // they keep the dependencies found when they last were.
+                Outcome::Passed if report.cached => {
+                    self.entries.remove(&path).map(|entry| entry.deps)
+                }
+                Outcome::Passed if cacheable(test.expected) => built.remove(&name.0),
+                Outcome::Passed
+                | Outcome::CreatedWip
+                | Outcome::Failed(_)
+                | Outcome::Ignored(_) => None,
+            };
+            match deps.and_then(|deps| Some((self.key(test, &deps)?, deps))) {
+                Some((key, deps)) => self.entries.insert(path, Entry { key, deps }),
+                None => self.entries.remove(&path),
+            };
+        }
+    }
+
+    pub fn save(&self) {
+        if let Ok(json) = serde_json::to_string_pretty(&self.entries) {
+            let _ = fs::write(&self.path, json);
+        }
+    }
+
+    fn key(&self, test: &Test, deps: &Deps) -> Option<String> {
+        let src_path = self.source_dir.join(&test.path);
+        let mut hasher = DefaultHasher::new();
+        self.base.hash(&mut hasher);
+        format!("{:?}", test).hash(&mut hasher);
+        if src_path.is_dir() {
+            hash_dir(&src_path, &mut hasher)?;
+        } else {
+            fs::read(&src_path).ok()?.hash(&mut hasher);
+        }
+        for file in &deps.files {
+            file.hash(&mut hasher);
+            fs::read(file).ok()?.hash(&mut hasher);
+        }
+        deps.env.hash(&mut hasher);
+        for extension in SNAPSHOT_EXTENSIONS {
+            fs::read(src_path.with_extension(extension))
+                .ok()
+                .hash(&mut hasher);
+        }
+        Some(format!("{:016x}", hasher.finish()))
+    }
+}
+
+// Reads the dep-info file that rustc wrote the last time it built the test
+// case. Cargo leaves it alone when the build is fresh, and the same bin name
+// may have been used by a different test case in an earlier run, so this picks
+// the newest one that lists the test case's own source file.
+fn dep_info(project: &Project, name: &Name, test: &Test) -> Option<Deps> {
+    let src_path = test.src_path(&project.source_dir);
+    let prefix = format!("{}-", name.0);
+    let mut candidates = Vec::new();
+    for entry in fs::read_dir(cargo::deps_dir(project)).ok()?.flatten() {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        let Some(_hash) = file_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".d"))
+        else {
+            continue;
+        };
+        if let Ok(modified) = entry.metadata().and_then(|metadata| metadata.modified()) {
+            candidates.push((modified, entry.path()));
+        }
+    }
+    candidates.sort_by_key(|(modified, _path)| Reverse(*modified));
+    candidates.into_iter().find_map(|(_modified, path)| {
+        let contents = fs::read_to_string(path).ok()?;
+        let deps = parse_dep_info(&contents, &project.dir);
+        deps.files.contains(&src_path).then_some(deps)
+    })
+}
+
+fn cacheable(expected: Expected) -> bool {
+    !matches!(expected, Expected::Pass | Expected::RunFail)
+}
+
+// Makefile syntax, as written by rustc's `--emit=dep-info`:
+//
+//     /path/to/deps/trybuild000-1f0e9ac3b966c4d6.d: tests/ui/main.rs tests/ui/module.rs
+//
+//     tests/ui/main.rs:
+//     tests/ui/module.rs:
+//
+//     # env-dep:VAR=value
+//
+// Every file the compiler read is listed as a target of its own with no
+// prerequisites, which avoids having to split the first line.
+fn parse_dep_info(contents: &str, dir: &Directory) -> Deps {
+    let mut deps = Deps::default();
+    for line in contents.lines() {
+        if let Some(env_dep) = line.strip_prefix("# env-dep:") {
+            deps.env.push(match env_dep.split_once('=') {
+                Some((var, value)) => (var.to_owned(), Some(unescape(value))),
+                None => (env_dep.to_owned(), None),
+            });
+        } else if let Some(file) = line.strip_suffix(':') {
+            if !file.is_empty() && !line.starts_with('#') {
+                deps.files.push(dir.join(file.replace("\\ ", " ")));
+            }
+        }
+    }
+    deps.files.sort();
+    deps.files.dedup();
+    deps
+}
+
+// rustc escapes the newlines and backslashes in the value of an env-dep.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some(ch) => unescaped.push(ch),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// Every file of a test case that is a directory.
+fn hash_dir(dir: &Path, hasher: &mut DefaultHasher) -> Option<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()
+        .ok()?;
+    entries.sort();
+    for path in entries {
+        path.file_name().hash(hasher);
+        if path.is_dir() {
+            hash_dir(&path, hasher)?;
+        } else {
+            fs::read(&path).ok()?.hash(hasher);
+        }
+    }
+    Some(())
+}
+
+fn base(project: &Project) -> Option<u64> {
+    if project.update == Update::Overwrite {
+        return None;
+    }
+
+    // There is no telling whether a closure passed to normalize_with does the
+    // same thing as in the previous run.
+    let has_closure = project
+        .normalizations
+        .iter()
+        .any(|normalization| matches!(normalization, Custom::Function(_)));
+    if has_closure {
+        return None;
+    }
+
+    let mut manifest = toml::Value::try_from(&project.manifest).ok()?;
+    if let Some(table) = manifest.as_table_mut() {
+        // Renumbering the test cases does not affect their outcome.
+        table.remove("bin");
+    }
+
+    let mut hasher = DefaultHasher::new();
+    rustc::version_verbose()?.hash(&mut hasher);
+    TARGET.hash(&mut hasher);
+    rustflags::toml(&[]).to_string().hash(&mut hasher);
+    manifest.to_string().hash(&mut hasher);
+    project.has_pass.hash(&mut hasher);
+    project.features.hash(&mut hasher);
+    format!("{:?}", project.normalizations).hash(&mut hasher);
+    project.snapshot_output.hash(&mut hasher);
+    project.snapshot_warnings.hash(&mut hasher);
+    project.deny_warnings.hash(&mut hasher);
+    project.json_diagnostics.hash(&mut hasher);
+    project.check_spans.hash(&mut hasher);
+    hash_fingerprints(project, &mut hasher);
+    Some(hasher.finish())
+}
+
+// Cargo rewrites the fingerprint files of a package whenever it rebuilds that
+// package, such as after the crate under test was edited.
+fn hash_fingerprints(project: &Project, hasher: &mut DefaultHasher) {
+    let Ok(entries) = fs::read_dir(cargo::fingerprint_dir(project)) else {
+        return;
+    };
+
+    let mut files = Vec::new();
+    for package_dir in entries.flatten() {
+        let dir_name = package_dir.file_name();
+        let dir_name = dir_name.to_string_lossy();
+        let package = dir_name
+            .rsplit_once('-')
+            .map_or(&*dir_name, |(name, _hash)| name);
+        if project.packages().any(|name| name == package) {
+            // Rebuilt every time the test cases are built.
+            continue;
+        }
+        let Ok(package_files) = fs::read_dir(package_dir.path()) else {
+            continue;
+        };
+        for file in package_files.flatten() {
+            let Ok(metadata) = file.metadata() else {
+                continue;
+            };
+            files.push((file.path(), metadata.len(), metadata.modified().ok()));
+        }
+    }
+
+    files.sort();
+    files.hash(hasher);
+}
+
+#[test]
+fn test_parse_dep_info() {
+    let dir = Directory::new("/project");
+    let contents = "\
+        /project/target/deps/trybuild000-1f0e9ac3b966c4d6.d: /tests/ui/main.rs tests/ui/my\\ module.rs\n\
+        \n\
+        /tests/ui/main.rs:\n\
+        tests/ui/my\\ module.rs:\n\
+        \n\
+        # env-dep:GREETING=hello\\nworld\n\
+        # env-dep:UNSET\n\
+    ";
+    let deps = parse_dep_info(contents, &dir);
+    assert_eq!(
+        deps.files,
+        [
+            PathBuf::from("/project/tests/ui/my module.rs"),
+            PathBuf::from("/tests/ui/main.rs"),
+        ],
+    );
+    assert_eq!(
+        deps.env,
+        [
+            ("GREETING".to_owned(), Some("hello\nworld".to_owned())),
+            ("UNSET".to_owned(), None),
+        ],
+    );
+}
@@ -1,11 +1,14 @@
+use crate::directives::Directives;
 use crate::directory::Directory;
 use crate::error::{Error, Result};
 use crate::manifest::Name;
+use crate::process;
 use crate::run::Project;
 use crate::rustflags;
 use serde_derive::Deserialize;
+use std::ffi::{OsStr, OsString};
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Output, Stdio};
 use std::{env, io, iter};
 use target_triple::TARGET;
@@ -92,17 +95,30 @@ pub(crate) fn build_dependencies(project: &mut Project) -> Result<()> {
         }
     }
 
+    // Dependencies are needed type-checked for the test cases that only get
+    // type-checked, and built for the ones that get built.
+    let subcommands: &[&str] = if project.has_pass {
+        &["build"]
+    } else if project.has_compile_fail_build {
+        &["check", "build"]
+    } else {
+        &["check"]
+    };
+
     let mut command = cargo(project);
-    command
-        .arg(if project.has_pass { "build" } else { "check" })
-        .args(target())
-        .arg("--bin")
-        .arg(&project.name)
-        .args(features(project));
+    for subcommand in subcommands {
+        command = cargo(project);
+        command
+            .arg(subcommand)
+            .args(target())
+            .arg("--bin")
+            .arg(&project.name)
+            .args(features(project));
 
-    let status = command.status().map_err(Error::Cargo)?;
-    if !status.success() {
-        return Err(Error::CargoFail);
+        let status = command.status().map_err(Error::Cargo)?;
+        if !status.success() {
+            return Err(Error::CargoFail);
+        }
     }
 
     // Check if this Cargo contains https://github.com/rust-lang/cargo/pull/10383
@@ -116,63 +132,235 @@ pub(crate) fn build_dependencies(project: &mut Project) -> Result<()> {
     Ok(())
 }
 
-pub(crate) fn build_test(project: &Project, name: &Name) -> Result<Output> {
+// So that building the test cases again reports their diagnostics again.
+fn clean_tests(project: &Project) {
     let _ = cargo(project)
         .arg("clean")
-        .arg("--package")
-        .arg(&project.name)
+        .args(
+            project
+                .packages()
+                .flat_map(|package| ["--package".to_owned(), package]),
+        )
         .arg("--color=never")
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .status();
+}
 
-    cargo_with_rustflags(project, &["--diagnostic-width=140"])
-        .arg(if project.has_pass { "build" } else { "check" })
-        .args(target())
+pub(crate) fn build_test(
+    project: &Project,
+    name: &Name,
+    directives: &Directives,
+    full_build: bool,
+) -> Result<Option<Output>> {
+    build_bin(project, name, name, directives, full_build)
+}
+
+// Builds the source of a `//@ run-rustfix` test case with the compiler's
+// suggestions applied, with the same features, flags and environment as the
+// test case itself.
+pub(crate) fn build_fixed_test(
+    project: &Project,
+    name: &Name,
+    directives: &Directives,
+    full_build: bool,
+) -> Result<Option<Output>> {
+    build_bin(project, &name.fixed(), name, directives, full_build)
+}
+
+fn build_bin(
+    project: &Project,
+    bin: &Name,
+    name: &Name,
+    directives: &Directives,
+    full_build: bool,
+) -> Result<Option<Output>> {
+    clean_tests(project);
+
+    let mut cmd = cargo_with_rustflags(project, &["--diagnostic-width=140"]);
+    if directives.compile_flags.is_empty() {
+        cmd.arg(if full_build { "build" } else { "check" });
+    } else {
+        // Unlike RUSTFLAGS, flags after `cargo rustc --` only apply to the test
+        // case and not to its dependencies.
+        cmd.arg("rustc");
+        if !full_build {
+            cmd.arg("--profile=check");
+        }
+    }
+    cmd.args(target())
+        .arg("--package")
+        .arg(project.package(bin))
         .arg("--bin")
-        .arg(name)
-        .args(features(project))
+        .arg(bin)
+        .args(features(project));
+
+    let extra_features = test_features(name, directives);
+    if !extra_features.is_empty() {
+        cmd.arg("--features").arg(extra_features.join(","));
+    }
+
+    cmd.envs(directives.rustc_env.iter().map(|(var, value)| (var, value)))
         .arg("--quiet")
         .arg("--color=never")
-        .arg("--message-format=json")
-        .output()
-        .map_err(Error::Cargo)
+        .arg("--message-format=json");
+    if !directives.compile_flags.is_empty() {
+        cmd.arg("--").args(&directives.compile_flags);
+    }
+
+    process::output(&mut cmd, project.timeout).map_err(Error::Cargo)
 }
 
-pub(crate) fn build_all_tests(project: &Project) -> Result<Output> {
-    let _ = cargo(project)
-        .arg("clean")
-        .arg("--package")
-        .arg(&project.name)
-        .arg("--color=never")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status();
+pub(crate) fn build_all_tests(
+    project: &Project,
+    names: &[&Name],
+    full_build: bool,
+) -> Result<Option<Output>> {
+    clean_tests(project);
 
-    cargo_with_rustflags(project, &["--diagnostic-width=140"])
-        .arg(if project.has_pass { "build" } else { "check" })
+    let mut cmd = cargo_with_rustflags(project, &["--diagnostic-width=140"]);
+    cmd.arg(if full_build { "build" } else { "check" })
         .args(target())
-        .arg("--bins")
+        .args(
+            names
+                .iter()
+                .flat_map(|name| [OsStr::new("--bin"), name.as_ref()]),
+        )
         .args(features(project))
         .arg("--quiet")
         .arg("--color=never")
         .arg("--message-format=json")
-        .arg("--keep-going")
-        .output()
-        .map_err(Error::Cargo)
+        .arg("--keep-going");
+
+    // As long as the test cases would get if each were built by itself.
+    let timeout = project.timeout.map(|timeout| {
+        let len = u32::try_from(names.len()).unwrap_or(u32::MAX);
+        timeout.saturating_mul(len)
+    });
+    process::output(&mut cmd, timeout).map_err(Error::Cargo)
 }
 
-pub(crate) fn run_test(project: &Project, name: &Name) -> Result<Output> {
-    cargo(project)
-        .arg("run")
+// Prints the test case's source after macro expansion to stdout. The flag is
+// unstable, so a stable compiler needs to be told to accept it anyway. That
+// goes for the whole build, and some build scripts such as proc-macro2's rerun
+// when RUSTC_BOOTSTRAP changes, so this uses a target directory of its own
+// rather than alternate with the other test cases in rebuilding everything.
+pub(crate) fn expand_test(
+    project: &Project,
+    name: &Name,
+    directives: &Directives,
+    bootstrap: bool,
+) -> Result<Option<Output>> {
+    // No need to clean first. With -Zunpretty, rustc writes no artifact for
+    // Cargo to consider fresh next time.
+    let mut cmd = cargo_with_rustflags(project, &["--diagnostic-width=140"]);
+    cmd.arg("rustc")
+        .arg("--profile=check")
         .args(target())
+        .arg("--package")
+        .arg(project.package(name))
         .arg("--bin")
         .arg(name)
-        .args(features(project))
+        .args(features(project));
+    let extra_features = test_features(name, directives);
+    if !extra_features.is_empty() {
+        cmd.arg("--features").arg(extra_features.join(","));
+    }
+    if bootstrap {
+        cmd.env("RUSTC_BOOTSTRAP", "1");
+        cmd.env(
+            "CARGO_TARGET_DIR",
+            path!(project.target_dir / "tests" / "trybuild-expand"),
+        );
+    }
+    cmd.envs(directives.rustc_env.iter().map(|(var, value)| (var, value)))
         .arg("--quiet")
         .arg("--color=never")
-        .output()
-        .map_err(Error::Cargo)
+        .arg("--")
+        .arg("-Zunpretty=expanded")
+        .args(&directives.compile_flags);
+
+    process::output(&mut cmd, project.timeout).map_err(Error::Cargo)
+}
+
+pub(crate) fn run_test(
+    project: &Project,
+    name: &Name,
+    executable: Option<&Path>,
+    snapshot: bool,
+) -> Command {
+    let mut cmd = match executable {
+        // Run the executable directly rather than through `cargo run`, which
+        // would print the test case's compiler warnings a second time.
+        Some(executable) => {
+            let mut cmd = Command::new(executable);
+            cmd.current_dir(&project.dir);
+            if let Some(cargo) = env::var_os("CARGO") {
+                cmd.env("CARGO", cargo);
+            }
+            cmd.env(
+                "CARGO_MANIFEST_DIR",
+                project.package_dir(name).as_ref() as &Path,
+            );
+            cmd.env("CARGO_PKG_NAME", project.package(name));
+            cmd.env("CARGO_PKG_VERSION", "0.0.0");
+            if let Some(dylib_path) = dylib_path(executable) {
+                cmd.env(DYLIB_PATH_VAR, dylib_path);
+            }
+            cmd
+        }
+        None => {
+            let mut cmd = cargo(project);
+            cmd.arg("run")
+                .args(target())
+                .arg("--package")
+                .arg(project.package(name))
+                .arg("--bin")
+                .arg(name)
+                .args(features(project))
+                .arg("--quiet")
+                .arg("--color=never");
+            cmd
+        }
+    };
+    if snapshot {
+        // Keep the test case's output stable for comparison against snapshot.
+        cmd.env("RUST_BACKTRACE", "0");
+    }
+    cmd
+}
+
+#[cfg(windows)]
+const DYLIB_PATH_VAR: &str = "PATH";
+#[cfg(target_os = "macos")]
+const DYLIB_PATH_VAR: &str = "DYLD_FALLBACK_LIBRARY_PATH";
+#[cfg(not(any(windows, target_os = "macos")))]
+const DYLIB_PATH_VAR: &str = "LD_LIBRARY_PATH";
+
+// Like `cargo run`, make dynamic libraries among the dependencies loadable by
+// the executable.
+fn dylib_path(executable: &Path) -> Option<OsString> {
+    let out_dir = executable.parent()?;
+    let mut paths = vec![out_dir.join("deps"), out_dir.to_owned()];
+    if let Some(existing) = env::var_os(DYLIB_PATH_VAR) {
+        paths.extend(env::split_paths(&existing));
+    }
+    env::join_paths(paths).ok()
+}
+
+pub(crate) fn fingerprint_dir(project: &Project) -> PathBuf {
+    path!(profile_dir(project) / ".fingerprint")
+}
+
+// Where rustc writes the dep-info files of the test cases.
+pub(crate) fn deps_dir(project: &Project) -> PathBuf {
+    path!(profile_dir(project) / "deps")
+}
+
+fn profile_dir(project: &Project) -> PathBuf {
+    let mut dir = path!(project.target_dir / "tests" / "trybuild");
+    dir.extend(target().get(1));
+    path!(dir / "debug")
 }
 
 pub(crate) fn metadata() -> Result<Metadata> {
@@ -185,7 +373,7 @@ pub(crate) fn metadata() -> Result<Metadata> {
 
     serde_json::from_slice(&output.stdout).map_err(|err| {
         print!("{}", String::from_utf8_lossy(&output.stderr));
-        Error::Metadata(err)
+        Error::Metadata(Box::new(err))
     })
 }
 
@@ -200,6 +388,16 @@ fn features(project: &Project) -> Vec<String> {
     }
 }
 
+// Features to enable only while building this one test case.
+fn test_features(name: &Name, directives: &Directives) -> Vec<String> {
+    let mut features = directives.features.clone();
+    if !directives.aux_crates.is_empty() {
+        // Enables the optional dependencies on this test case's aux crates.
+        features.push(name.0.clone());
+    }
+    features
+}
+
 fn target() -> Vec<&'static str> {
     // When --target flag is passed, cargo does not pass RUSTFLAGS to rustc when
     // building proc-macro and build script even if the host and target triples
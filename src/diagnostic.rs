@@ -0,0 +1,117 @@
+use crate::normalize::{self, Context};
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// Subset of rustc's JSON diagnostic format.
+// https://doc.rust-lang.org/rustc/json.html
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct Diagnostic {
+    pub message: String,
+    pub code: Option<DiagnosticCode>,
+    pub level: String,
+    pub spans: Vec<DiagnosticSpan>,
+    pub children: Vec<Diagnostic>,
+    pub rendered: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct DiagnosticCode {
+    pub code: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct DiagnosticSpan {
+    pub file_name: PathBuf,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: usize,
+    pub column_start: usize,
+    pub is_primary: bool,
+    pub label: Option<String>,
+    pub suggested_replacement: Option<String>,
+    pub suggestion_applicability: Option<String>,
+    pub expansion: Option<Box<DiagnosticSpanMacroExpansion>>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct DiagnosticSpanMacroExpansion {
+    // Where the macro was invoked, which is itself inside the expansion of
+    // another macro if the invocation was.
+    pub span: DiagnosticSpan,
+}
+
+impl Diagnostic {
+    pub fn primary_span(&self) -> Option<&DiagnosticSpan> {
+        self.spans.iter().find(|span| span.is_primary)
+    }
+}
+
+// What a *.diagnostics.json snapshot keeps of each diagnostic. Everything to do
+// with rendering, such as the source snippet and the terminal width, is left
+// out, and so are suggestions.
+#[derive(Serialize)]
+struct Snapshot {
+    level: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    labels: Vec<Label>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<Snapshot>,
+}
+
+#[derive(Serialize)]
+struct Label {
+    location: String,
+    label: String,
+}
+
+pub(crate) fn snapshot(diagnostics: &[Diagnostic], context: Context) -> String {
+    let snapshots: Vec<Snapshot> = diagnostics
+        .iter()
+        .filter(|diag| !is_summary(diag))
+        .map(|diag| to_snapshot(diag, context))
+        .collect();
+    let json = serde_json::to_string_pretty(&snapshots).unwrap_or_default();
+    normalize::diagnostics_json(&json, context)
+}
+
+fn to_snapshot(diag: &Diagnostic, context: Context) -> Snapshot {
+    let location = |span: &DiagnosticSpan| {
+        normalize::location(&span.file_name, span.line_start, span.column_start, context)
+    };
+    Snapshot {
+        level: diag.level.clone(),
+        code: diag.code.as_ref().map(|code| code.code.clone()),
+        message: normalize::message(&diag.message, context),
+        location: diag.primary_span().map(location),
+        labels: diag
+            .spans
+            .iter()
+            .filter_map(|span| {
+                Some(Label {
+                    location: location(span),
+                    label: normalize::message(span.label.as_deref()?, context),
+                })
+            })
+            .collect(),
+        children: diag
+            .children
+            .iter()
+            .map(|child| to_snapshot(child, context))
+            .collect(),
+    }
+}
+
+// "aborting due to 2 previous errors; 1 warning emitted" and the like, which
+// only restate the other diagnostics.
+pub(crate) fn is_summary(diag: &Diagnostic) -> bool {
+    diag.spans.is_empty()
+        && diag.children.is_empty()
+        && (diag.message.starts_with("aborting due to")
+            || diag.message.ends_with("warning emitted")
+            || diag.message.ends_with("warnings emitted"))
+}
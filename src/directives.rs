@@ -0,0 +1,246 @@
+use crate::error::{Error, Result};
+use crate::manifest::Edition;
+use crate::rustc::Version;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Comments in the style of rustc's compiletest that configure how an individual
+// test case is built.
+//
+//     //@ edition: 2018
+//     //@ compile-flags: --cfg=foo -Zunstable-options
+//     //@ rustc-env: MY_VAR=1
+//     //@ features: serde, std
+//     //@ aux-crate: helper=aux/helper.rs
+//     //@ ignore: waiting on rust-lang/rust#12345
+//     //@ only-nightly
+//     //@ min-rustc: 1.78
+//     //@ run-rustfix
+//     //@ check-spans
+//     //@ error-codes: E0277, E0599
+//
+// An aux crate's own source file may in turn contain `//@ proc-macro`.
+//
+// Directives go in a header at the top of the file. The first line that is
+// neither blank nor a `//@` comment ends the header, so that `//@` further down,
+// such as inside a string literal or a doc example, is left alone.
+#[derive(Default, Debug)]
+pub(crate) struct Directives {
+    pub edition: Option<Edition>,
+    pub compile_flags: Vec<String>,
+    pub rustc_env: Vec<(String, String)>,
+    pub features: Vec<String>,
+    pub aux_crates: Vec<AuxCrate>,
+    pub ignore: Option<String>,
+    pub only_nightly: bool,
+    pub min_rustc: Option<Version>,
+    pub run_rustfix: bool,
+    pub build_fixed: bool,
+    pub check_spans: bool,
+    pub error_codes: Vec<String>,
+    pub proc_macro: bool,
+}
+
+impl Directives {
+    // Whether anything differs from how the other test cases are built.
+    pub fn needs_own_build(&self) -> bool {
+        self.edition.is_some()
+            || !self.compile_flags.is_empty()
+            || !self.rustc_env.is_empty()
+            || !self.features.is_empty()
+            || !self.aux_crates.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct AuxCrate {
+    pub name: String,
+    // Relative to the directory containing the test case.
+    pub path: PathBuf,
+}
+
+// Directives of a test case, which is either a file or a directory containing
+// main.rs. A test case that does not exist has none, and is reported as missing
+// once it runs.
+pub(crate) fn read(path: &Path) -> Result<Directives> {
+    let main_path = path.join("main.rs");
+    let src_path = if path.is_dir() { &main_path } else { path };
+    let Ok(source) = fs::read_to_string(src_path) else {
+        return Ok(Directives::default());
+    };
+    let directives = parse(&source).map_err(|msg| Error::Directive(path.to_owned(), msg))?;
+    if directives.proc_macro {
+        let msg = "`//@ proc-macro` only applies to aux crates".to_owned();
+        return Err(Error::Directive(path.to_owned(), msg));
+    }
+    Ok(directives)
+}
+
+pub(crate) fn parse(source: &str) -> std::result::Result<Directives, String> {
+    let mut directives = Directives::default();
+
+    for line in source.lines() {
+        let line = line.trim_start();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(directive) = line.strip_prefix("//@") else {
+            break;
+        };
+        let (key, value) = match directive.split_once(':') {
+            Some((key, value)) => (key.trim(), Some(value.trim())),
+            None => (directive.trim(), None),
+        };
+
+        match (key, value) {
+            ("edition", Some(value)) => {
+                directives.edition = Some(match value {
+                    "2015" => Edition::E2015,
+                    "2018" => Edition::E2018,
+                    "2021" => Edition::E2021,
+                    "2024" => Edition::E2024,
+                    _ => return Err(format!("unrecognized edition `{}`", value)),
+                });
+            }
+            ("compile-flags", Some(value)) => {
+                let flags = value.split_whitespace().map(str::to_owned);
+                directives.compile_flags.extend(flags);
+            }
+            ("rustc-env", Some(value)) => {
+                let Some((var, value)) = value.split_once('=') else {
+                    return Err(format!(
+                        "expected `//@ rustc-env: VAR=value`, found `//@{}`",
+                        directive,
+                    ));
+                };
+                directives
+                    .rustc_env
+                    .push((var.trim().to_owned(), value.trim().to_owned()));
+            }
+            ("features", Some(value)) => {
+                let features = value
+                    .split(|ch: char| ch == ',' || ch.is_whitespace())
+                    .filter(|feature| !feature.is_empty())
+                    .map(str::to_owned);
+                directives.features.extend(features);
+            }
+            ("ignore", value) => directives.ignore = Some(value.unwrap_or("").to_owned()),
+            ("only-nightly", None) => directives.only_nightly = true,
+            ("min-rustc", Some(value)) => match value.parse() {
+                Ok(version) => directives.min_rustc = Some(version),
+                Err(()) => return Err(format!("unrecognized rustc version `{}`", value)),
+            },
+            ("run-rustfix", None) => {
+                directives.run_rustfix = true;
+                directives.build_fixed = true;
+            }
+            ("run-rustfix", Some("no-build")) => directives.run_rustfix = true,
+            ("check-spans", None) => directives.check_spans = true,
+            ("error-codes", Some(value)) => {
+                let codes = value
+                    .split(|ch: char| ch == ',' || ch.is_whitespace())
+                    .filter(|code| !code.is_empty())
+                    .map(str::to_owned);
+                directives.error_codes.extend(codes);
+            }
+            ("aux-crate", Some(value)) => {
+                let Some((name, path)) = value.split_once('=') else {
+                    return Err(format!(
+                        "expected `//@ aux-crate: name=path`, found `//@{}`",
+                        directive,
+                    ));
+                };
+                let name = name.trim();
+                if !is_crate_name(name) {
+                    return Err(format!("invalid aux crate name `{}`", name));
+                }
+                directives.aux_crates.push(AuxCrate {
+                    name: name.to_owned(),
+                    path: PathBuf::from(path.trim()),
+                });
+            }
+            ("proc-macro", None) => directives.proc_macro = true,
+            _ => return Err(format!("unrecognized directive `//@{}`", directive)),
+        }
+    }
+
+    Ok(directives)
+}
+
+fn is_crate_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+#[test]
+fn test_parse() {
+    let source = "\
+        //@ aux-crate: helper=aux/helper.rs\n\
+        //@ aux-crate:derive = aux/derive.rs\n\
+        // @ not a directive\n\
+        fn main() {}\n\
+    ";
+    let directives = parse(source).unwrap();
+    let aux_crates: Vec<_> = directives
+        .aux_crates
+        .iter()
+        .map(|aux| (aux.name.as_str(), aux.path.to_str().unwrap()))
+        .collect();
+    assert_eq!(
+        aux_crates,
+        [("helper", "aux/helper.rs"), ("derive", "aux/derive.rs")],
+    );
+    assert!(!directives.proc_macro);
+
+    let source = "\
+        //@ edition: 2018\n\
+        //@ compile-flags: --cfg=foo -Dwarnings\n\
+        //@ rustc-env: GREETING = hello\n\
+        //@ features: serde, std\n\
+        //@ ignore\n\
+        //@ min-rustc: 1.78\n\
+        //@ run-rustfix: no-build\n\
+        //@ error-codes: E0277,E0599 uncoded\n\
+    ";
+    let directives = parse(source).unwrap();
+    assert!(matches!(directives.edition, Some(Edition::E2018)));
+    assert_eq!(directives.compile_flags, ["--cfg=foo", "-Dwarnings"]);
+    assert_eq!(
+        directives.rustc_env,
+        [("GREETING".to_owned(), "hello".to_owned())],
+    );
+    assert_eq!(directives.features, ["serde", "std"]);
+    assert_eq!(directives.ignore.as_deref(), Some(""));
+    assert_eq!(
+        directives.min_rustc,
+        Some(Version {
+            minor: 78,
+            patch: 0
+        })
+    );
+    assert!(directives.run_rustfix && !directives.build_fixed);
+    assert_eq!(directives.error_codes, ["E0277", "E0599", "uncoded"]);
+    assert!(directives.needs_own_build());
+
+    let source = "\
+        //@ edition: 2018\n\
+        \n\
+        fn main() {}\n\
+        //@ frobnicate\n\
+    ";
+    let directives = parse(source).unwrap();
+    assert!(matches!(directives.edition, Some(Edition::E2018)));
+
+    let directives = parse("// comment\n//@ compile-flags: --cfg=foo\n").unwrap();
+    assert!(directives.compile_flags.is_empty());
+
+    let err = parse("//@ edition: 2019").unwrap_err();
+    assert_eq!(err, "unrecognized edition `2019`");
+    let err = parse("//@ aux-crate: my-helper=aux/helper.rs").unwrap_err();
+    assert_eq!(err, "invalid aux crate name `my-helper`");
+    let err = parse("//@ frobnicate").unwrap_err();
+    assert_eq!(err, "unrecognized directive `//@ frobnicate`");
+}
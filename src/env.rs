@@ -1,11 +1,15 @@
 use crate::error::{Error, Result};
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(PartialEq, Default, Debug)]
 pub(crate) enum Update {
     #[default]
     Wip,
     Overwrite,
+    Prune,
+    Review,
 }
 
 impl Update {
@@ -17,7 +21,67 @@ impl Update {
         match var.as_os_str().to_str() {
             Some("wip") => Ok(Update::Wip),
             Some("overwrite") => Ok(Update::Overwrite),
+            Some("prune") => Ok(Update::Prune),
+            Some("review") => Ok(Update::Review),
             _ => Err(Error::UpdateVar(var)),
         }
     }
 }
+
+#[derive(Debug)]
+pub(crate) enum ReportFormat {
+    Junit,
+    Json,
+}
+
+#[derive(Debug)]
+pub(crate) struct ReportOutput {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+impl ReportOutput {
+    pub fn env() -> Result<Option<Self>> {
+        let Some(var) = env::var_os("TRYBUILD_REPORT") else {
+            return Ok(None);
+        };
+
+        let (format, path) = match var.to_str().and_then(|var| var.split_once(':')) {
+            // Every #[test] function writes a report of its own.
+            Some(("junit", path)) if path.contains("{name}") => (ReportFormat::Junit, path),
+            Some(("json", path)) if path.contains("{name}") => (ReportFormat::Json, path),
+            _ => return Err(Error::ReportVar(var)),
+        };
+
+        Ok(Some(ReportOutput {
+            format,
+            path: PathBuf::from(path),
+        }))
+    }
+}
+
+pub(crate) fn cache() -> Result<bool> {
+    let Some(var) = env::var_os("TRYBUILD_CACHE") else {
+        return Ok(false);
+    };
+
+    match var.as_os_str().to_str() {
+        Some("0") => Ok(false),
+        Some("1") => Ok(true),
+        _ => Err(Error::CacheVar(var)),
+    }
+}
+
+pub(crate) fn timeout() -> Result<Option<Duration>> {
+    let Some(var) = env::var_os("TRYBUILD_TIMEOUT") else {
+        return Ok(None);
+    };
+
+    match var.to_str().map(str::parse::<f64>) {
+        Some(Ok(secs)) => match Duration::try_from_secs_f64(secs) {
+            Ok(timeout) => Ok(Some(timeout)),
+            Err(_) => Err(Error::TimeoutVar(var)),
+        },
+        _ => Err(Error::TimeoutVar(var)),
+    }
+}
@@ -3,26 +3,75 @@ use std::ffi::OsString;
 use std::fmt::{self, Display};
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
+/// Failure of an individual test case, or of trybuild's preparation before
+/// running any test cases.
+///
+/// Errors coming from trybuild's dependencies, such as a malformed Cargo.toml,
+/// are boxed so that their types are not part of this crate's API.
 #[derive(Debug)]
-pub(crate) enum Error {
+#[non_exhaustive]
+pub enum Error {
+    /// An error did not point at the offending code, under `check_spans`.
+    BadSpans,
+    /// Unrecognized value of the `TRYBUILD_CACHE` environment variable.
+    CacheVar(OsString),
+    /// Cargo could not be executed.
     Cargo(io::Error),
+    /// Cargo ran but failed, for example when a pass test did not compile.
     CargoFail,
+    /// Malformed `//@` directive in the given test case.
+    Directive(PathBuf, String),
+    /// A run_fail test exited with a different code than the expected one, or
+    /// was terminated by a signal.
+    ExitCode(i32, Option<i32>),
+    /// The source of a `//@ run-rustfix` test case with the compiler's
+    /// suggestions applied did not compile.
+    FixedFailed,
+    /// A Cargo.toml could not be read or parsed.
     GetManifest(PathBuf, Box<Error>),
-    Glob(GlobError),
+    /// A file matched by a glob could not be read.
+    Glob(Box<dyn std::error::Error + Send + Sync>),
+    /// Some other I/O error.
     Io(io::Error),
-    Metadata(serde_json::Error),
+    /// The output of `cargo metadata` could not be parsed.
+    Metadata(Box<dyn std::error::Error + Send + Sync>),
+    /// The output did not match the expected output.
     Mismatch,
+    /// Cargo.toml inherits its edition from the workspace, which has none.
     NoWorkspaceManifest,
+    /// The given file could not be opened.
     Open(PathBuf, io::Error),
-    Pattern(PatternError),
+    /// Malformed glob pattern.
+    Pattern(Box<dyn std::error::Error + Send + Sync>),
+    /// The directory of the crate under test could not be found.
     ProjectDir,
+    /// A snapshot file could not be read.
     ReadStderr(io::Error),
+    /// Unrecognized value of the `TRYBUILD_REPORT` environment variable.
+    ReportVar(OsString),
+    /// A pass test failed at runtime.
     RunFailed,
+    /// A run_fail test ran successfully.
+    RunSucceeded,
+    /// A compile_fail test compiled.
     ShouldNotHaveCompiled,
-    TomlDe(toml::de::Error),
-    TomlSer(toml::ser::Error),
+    /// The compiler or the test case did not finish within the timeout.
+    Timeout(PathBuf, Duration),
+    /// Unrecognized value of the `TRYBUILD_TIMEOUT` environment variable.
+    TimeoutVar(OsString),
+    /// A Cargo.toml could not be parsed.
+    TomlDe(Box<dyn std::error::Error + Send + Sync>),
+    /// The generated Cargo.toml could not be serialized.
+    TomlSer(Box<dyn std::error::Error + Send + Sync>),
+    /// Unrecognized value of the `TRYBUILD` environment variable.
     UpdateVar(OsString),
+    /// A test case that compiled had warnings, under `deny_warnings`.
+    Warnings,
+    /// The report requested by `TRYBUILD_REPORT` could not be written.
+    WriteReport(PathBuf, io::Error),
+    /// A snapshot file could not be written.
     WriteStderr(io::Error),
 }
 
@@ -33,8 +82,29 @@ impl Display for Error {
         use self::Error::*;
 
         match self {
+            BadSpans => write!(f, "error does not point at the offending code"),
+            CacheVar(var) => write!(
+                f,
+                r#"unrecognized value of TRYBUILD_CACHE: {:?} is not "0" or "1""#,
+                var.to_string_lossy(),
+            ),
             Cargo(e) => write!(f, "failed to execute cargo: {}", e),
             CargoFail => write!(f, "cargo reported an error"),
+            Directive(path, msg) => write!(f, "{}: {}", path.display(), msg),
+            ExitCode(expected, Some(actual)) => write!(
+                f,
+                "expected test case to exit with code {}, but it exited with code {}",
+                expected, actual,
+            ),
+            ExitCode(expected, None) => write!(
+                f,
+                "expected test case to exit with code {}, but it was terminated by a signal",
+                expected,
+            ),
+            FixedFailed => write!(
+                f,
+                "test case with the compiler's suggestions applied failed to compile",
+            ),
             GetManifest(path, e) => write!(f, "failed to read manifest {}: {}", path.display(), e),
             Glob(e) => write!(f, "{}", e),
             Io(e) => write!(f, "{}", e),
@@ -45,42 +115,88 @@ impl Display for Error {
             Pattern(e) => write!(f, "{}", e),
             ProjectDir => write!(f, "failed to determine name of project dir"),
             ReadStderr(e) => write!(f, "failed to read stderr file: {}", e),
+            ReportVar(var) => write!(
+                f,
+                r#"unrecognized value of TRYBUILD_REPORT: {:?} is not of the form "junit:path" or "json:path" with {{name}} in the path"#,
+                var.to_string_lossy(),
+            ),
             RunFailed => write!(f, "execution of the test case was unsuccessful"),
+            RunSucceeded => write!(
+                f,
+                "expected test case to fail at runtime, but it ran successfully"
+            ),
             ShouldNotHaveCompiled => {
                 write!(f, "expected test case to fail to compile, but it succeeded")
             }
+            Timeout(path, timeout) => write!(
+                f,
+                "{} did not finish within {:?} and was killed",
+                path.display(),
+                timeout,
+            ),
+            TimeoutVar(var) => write!(
+                f,
+                "unrecognized value of TRYBUILD_TIMEOUT: {:?} is not a number of seconds",
+                var.to_string_lossy(),
+            ),
             TomlDe(e) => write!(f, "{}", e),
             TomlSer(e) => write!(f, "{}", e),
             UpdateVar(var) => write!(
                 f,
-                r#"unrecognized value of TRYBUILD: {:?} is not one of "wip", "overwrite""#,
+                r#"unrecognized value of TRYBUILD: {:?} is not one of "wip", "overwrite", "prune", "review""#,
                 var.to_string_lossy(),
             ),
+            Warnings => write!(f, "expected test case to compile without warnings"),
+            WriteReport(path, e) => {
+                write!(f, "failed to write report {}: {}", path.display(), e)
+            }
             WriteStderr(e) => write!(f, "failed to write stderr file: {}", e),
         }
     }
 }
 
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use self::Error::*;
+
+        match self {
+            Cargo(e) | Io(e) | Open(_, e) | ReadStderr(e) | WriteStderr(e) => Some(e),
+            WriteReport(_, e) => Some(e),
+            GetManifest(_, e) => Some(e),
+            Glob(e) | Metadata(e) | Pattern(e) | TomlDe(e) | TomlSer(e) => Some(&**e),
+            _ => None,
+        }
+    }
+}
+
 impl Error {
-    pub fn already_printed(&self) -> bool {
+    pub(crate) fn already_printed(&self) -> bool {
         use self::Error::*;
 
         matches!(
             self,
-            CargoFail | Mismatch | RunFailed | ShouldNotHaveCompiled
+            BadSpans
+                | CargoFail
+                | ExitCode(..)
+                | FixedFailed
+                | Mismatch
+                | RunFailed
+                | RunSucceeded
+                | ShouldNotHaveCompiled
+                | Warnings
         )
     }
 }
 
 impl From<GlobError> for Error {
     fn from(err: GlobError) -> Self {
-        Error::Glob(err)
+        Error::Glob(Box::new(err))
     }
 }
 
 impl From<PatternError> for Error {
     fn from(err: PatternError) -> Self {
-        Error::Pattern(err)
+        Error::Pattern(Box::new(err))
     }
 }
 
@@ -92,12 +208,12 @@ impl From<io::Error> for Error {
 
 impl From<toml::de::Error> for Error {
     fn from(err: toml::de::Error) -> Self {
-        Error::TomlDe(err)
+        Error::TomlDe(Box::new(err))
     }
 }
 
 impl From<toml::ser::Error> for Error {
     fn from(err: toml::ser::Error) -> Self {
-        Error::TomlSer(err)
+        Error::TomlSer(Box::new(err))
     }
 }
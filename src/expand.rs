@@ -1,6 +1,7 @@
+use crate::directives::{self, Directives};
 use crate::error::{Error, Result};
 use crate::manifest::Name;
-use crate::Test;
+use crate::{Expected, Test};
 use std::collections::BTreeMap as Map;
 use std::path::PathBuf;
 
@@ -9,6 +10,7 @@ pub(crate) struct ExpandedTest {
     pub name: Name,
     pub test: Test,
     pub error: Option<Error>,
+    pub directives: Directives,
     is_from_glob: bool,
 }
 
@@ -19,9 +21,12 @@ pub(crate) fn expand_globs(tests: &[Test]) -> Vec<ExpandedTest> {
         match test.path.to_str() {
             Some(utf8) if utf8.contains('*') => match glob(utf8) {
                 Ok(paths) => {
-                    let expected = test.expected;
                     for path in paths {
-                        set.insert(Test { path, expected }, None, true);
+                        let test = Test {
+                            path,
+                            ..test.clone()
+                        };
+                        set.insert(test, None, true);
                     }
                 }
                 Err(error) => set.insert(test.clone(), Some(error), false),
@@ -46,15 +51,26 @@ impl ExpandedTestSet {
         }
     }
 
-    fn insert(&mut self, test: Test, error: Option<Error>, is_from_glob: bool) {
+    fn insert(&mut self, mut test: Test, error: Option<Error>, is_from_glob: bool) {
         if let Some(&i) = self.path_to_index.get(&test.path) {
             let prev = &mut self.vec[i];
             if prev.is_from_glob {
-                prev.test.expected = test.expected;
+                add_error_codes(&mut test, &prev.directives);
+                prev.test = test;
                 return;
             }
         }
 
+        let (directives, error) = match error {
+            Some(error) => (Directives::default(), Some(error)),
+            None => match directives::read(&test.path) {
+                Ok(directives) => (directives, None),
+                Err(error) => (Directives::default(), Some(error)),
+            },
+        };
+
+        add_error_codes(&mut test, &directives);
+
         let index = self.vec.len();
         let name = Name(format!("trybuild{:03}", index));
         self.path_to_index.insert(test.path.clone(), index);
@@ -62,15 +78,30 @@ impl ExpandedTestSet {
             name,
             test,
             error,
+            directives,
             is_from_glob,
         });
     }
 }
 
+// Codes from `//@ error-codes` are expected in addition to the ones passed to
+// compile_fail_with_codes.
+fn add_error_codes(test: &mut Test, directives: &Directives) {
+    if let Expected::CompileFailWithCodes = test.expected {
+        test.codes.extend(directives.error_codes.iter().cloned());
+    }
+}
+
 fn glob(pattern: &str) -> Result<Vec<PathBuf>> {
     let mut paths = glob::glob(pattern)?
         .map(|entry| entry.map_err(Error::from))
         .collect::<Result<Vec<PathBuf>>>()?;
+    // Snapshots of expand test cases, which `*.rs` would match too.
+    paths.retain(|path| {
+        !path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(".expanded.rs"))
+    });
     paths.sort();
     Ok(paths)
 }
@@ -0,0 +1,230 @@
+use crate::expand::{expand_globs, ExpandedTest};
+use crate::orphans;
+use crate::report::{Outcome, Report};
+use crate::Runner;
+use std::env;
+use std::io::{self, Write};
+use std::process;
+use std::time::Instant;
+
+// Command line of a libtest test binary, as passed by `cargo test` or
+// cargo-nextest to a test target with `harness = false`.
+//
+//     $ cargo test --test ui -- compile_fail/tuple --skip unit
+//     $ cargo test --test ui -- --list --format terse
+#[derive(Debug, Default)]
+pub(crate) struct Args {
+    list: bool,
+    exact: bool,
+    ignored: bool,
+    include_ignored: bool,
+    terse: bool,
+    filters: Vec<String>,
+    skip: Vec<String>,
+}
+
+impl Args {
+    pub fn from_env() -> Self {
+        match Args::parse(env::args().skip(1)) {
+            Ok(args) => args,
+            Err(message) => {
+                eprintln!("error: {}", message);
+                process::exit(101);
+            }
+        }
+    }
+
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_owned())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("Argument to option '{}' missing", flag))
+            };
+
+            match flag {
+                "--list" => parsed.list = true,
+                "--exact" => parsed.exact = true,
+                "--ignored" => parsed.ignored = true,
+                "--include-ignored" => parsed.include_ignored = true,
+                "--skip" => parsed.skip.push(value()?),
+                "--format" => match value()?.as_str() {
+                    "pretty" => parsed.terse = false,
+                    "terse" => parsed.terse = true,
+                    other => {
+                        return Err(format!(
+                            "argument for --format must be pretty or terse (was {})",
+                            other,
+                        ));
+                    }
+                },
+                // Accepted for compatibility with libtest, but trybuild
+                // decides on its own how to run the test cases and how to
+                // color its output.
+                "--color" | "--logfile" | "--shuffle-seed" | "--test-threads" | "-Z" => {
+                    value()?;
+                }
+                "--bench"
+                | "--ensure-time"
+                | "--force-run-in-process"
+                | "--nocapture"
+                | "--quiet"
+                | "--report-time"
+                | "--show-output"
+                | "--shuffle"
+                | "--test"
+                | "-q" => {}
+                _ if flag.starts_with('-') => {
+                    return Err(format!("Unrecognized option: '{}'", flag));
+                }
+                _ => parsed.filters.push(arg),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn filter(&self, tests: &mut Vec<ExpandedTest>) {
+        tests.retain(|t| {
+            let ignored = t.directives.ignore.is_some();
+            self.is_match(&t.test.path.to_string_lossy(), ignored)
+        });
+    }
+
+    // Whether test cases with an `//@ ignore` directive are run anyway.
+    pub fn run_ignored(&self) -> bool {
+        self.ignored || self.include_ignored
+    }
+
+    fn is_match(&self, name: &str, ignored: bool) -> bool {
+        if self.ignored && !self.include_ignored && !ignored {
+            return false;
+        }
+
+        let matches = |filter: &String| {
+            if self.exact {
+                name == filter
+            } else {
+                name.contains(filter.as_str())
+            }
+        };
+
+        (self.filters.is_empty() || self.filters.iter().any(matches))
+            && !self.skip.iter().any(matches)
+    }
+}
+
+// Runs the test cases of a `harness = false` test target, reporting the result
+// in the format of libtest. Returns whether all selected test cases passed.
+pub(crate) fn run(runner: &mut Runner, args: Args) -> bool {
+    let mut tests = expand_globs(&runner.tests);
+    let orphans = orphans::find(&runner.tests, &tests);
+    let total = tests.len();
+    args.filter(&mut tests);
+    let filtered_out = total - tests.len();
+
+    let mut stdout = io::stdout().lock();
+
+    if args.list {
+        runner.finished = true;
+        for t in &tests {
+            let _ = writeln!(stdout, "{}: test", t.test.path.display());
+        }
+        if !args.terse {
+            let _ = writeln!(stdout);
+            let _ = writeln!(stdout, "{}, 0 benchmarks", plural(tests.len(), "test"));
+        }
+        return true;
+    }
+
+    let _ = writeln!(stdout);
+    let _ = writeln!(stdout, "running {}", plural(tests.len(), "test"));
+    let _ = stdout.flush();
+
+    let start = Instant::now();
+    let report = if tests.is_empty() {
+        // Nothing to build.
+        runner.finished = true;
+        Report { tests: Vec::new() }
+    } else {
+        runner.harness = Some(args);
+        let Ok(report) = runner.run_selected(tests, orphans) else {
+            return false;
+        };
+        report
+    };
+
+    let failures: Vec<_> = report
+        .tests
+        .iter()
+        .filter(|test| !matches!(test.outcome, Outcome::Passed | Outcome::Ignored(_)))
+        .collect();
+    let ignored = report.ignored();
+
+    if !failures.is_empty() {
+        let _ = writeln!(stdout);
+        let _ = writeln!(stdout, "failures:");
+        for test in &failures {
+            let _ = writeln!(stdout, "    {}", test.path.display());
+        }
+    }
+
+    let _ = writeln!(stdout);
+    let _ = writeln!(
+        stdout,
+        "test result: {}. {} passed; {} failed; {} ignored; 0 measured; {} filtered out; finished in {:.2}s",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        report.tests.len() - failures.len() - ignored,
+        failures.len(),
+        ignored,
+        filtered_out,
+        start.elapsed().as_secs_f64(),
+    );
+    let _ = writeln!(stdout);
+
+    failures.is_empty()
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+#[test]
+fn test_parse() {
+    let args = [
+        "tuple",
+        "--exact",
+        "--skip",
+        "unit",
+        "--format=terse",
+        "--test-threads",
+        "4",
+    ];
+    let args = Args::parse(args.map(str::to_owned)).unwrap();
+    assert_eq!(args.filters, ["tuple"]);
+    assert_eq!(args.skip, ["unit"]);
+    assert!(args.exact && args.terse && !args.list);
+
+    assert!(args.is_match("tuple", false));
+    assert!(!args.is_match("tests/ui/tuple.rs", false));
+
+    let args = Args::parse(["--ignored".to_owned()]).unwrap();
+    assert!(args.is_match("tuple", true));
+    assert!(!args.is_match("tuple", false));
+    assert!(args.run_ignored());
+
+    let err = Args::parse(["--frobnicate".to_owned()]).unwrap_err();
+    assert_eq!(err, "Unrecognized option: '--frobnicate'");
+}
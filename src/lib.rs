@@ -48,6 +48,14 @@
 //! Dependencies listed under `[dev-dependencies]` in the project's Cargo.toml
 //! are accessible from within the test cases.
 //!
+//! A test case that needs more than one source file, for example to exercise a
+//! macro across `mod` boundaries or `#[path]` includes, can be a directory
+//! instead: `t.compile_fail("tests/ui/multi-file/")` compiles
+//! _tests/ui/multi-file/main.rs_ as the crate root, with the other files in the
+//! directory available as its modules, and compares against
+//! _tests/ui/multi-file.stderr_ next to the directory. Line numbers are kept in
+//! the expected output for every file inside the directory.
+//!
 //! <p align="center">
 //! <img src="https://user-images.githubusercontent.com/1940490/57186574-76469e00-6e96-11e9-8cb5-b63b657170c9.png" width="700">
 //! </p>
@@ -64,6 +72,21 @@
 //! <img src="https://user-images.githubusercontent.com/1940490/57186576-7b0b5200-6e96-11e9-8bfd-2de705125108.png" width="700">
 //! </p>
 //!
+//! Procedural macros are expected to point their errors at the tokens that are
+//! at fault. After calling `t.check_spans()`, or in a test case containing
+//! `//@ check-spans`, a test case also fails if the primary span of any error
+//! lies outside of the test case, lands on the macro invocation as it does for
+//! `Span::call_site()`, or covers an entire item such as the whole input of a
+//! derive macro. This is checked before comparing against the _*.stderr_ file.
+//!
+//! Test cases are only type-checked with `cargo check`, unless the same
+//! `TestCases` also has pass tests. Some errors never show up that way: errors
+//! evaluating an associated const of a generic type, which only happen once the
+//! generic code is instantiated, and link errors. Register those test cases
+//! with `t.compile_fail_build("tests/ui/post-mono.rs")` to always have them
+//! built with `cargo build`. This does not change how the other test cases are
+//! compiled.
+//!
 //! <br>
 //!
 //! # Pass tests
@@ -99,6 +122,292 @@
 //! <img src="https://user-images.githubusercontent.com/1940490/57186580-7f376f80-6e96-11e9-9cae-8257609269ef.png" width="700">
 //! </p>
 //!
+//! By default whatever the test case prints is displayed but not checked. After
+//! calling `t.snapshot_output()`, the stdout and stderr of each pass test are
+//! compared against adjacently named _*.stdout_ and _*.run.stderr_ files
+//! instead, which are created and updated in the same way as _*.stderr_ files
+//! as described under [Workflow](#workflow). A missing file is equivalent to
+//! expecting no output.
+//!
+//! Test cases that are expected to compile but then fail at runtime, for
+//! example by panicking, can be registered with `t.run_fail(path)`, or with
+//! `t.run_fail_with_code(path, code)` to additionally require a particular exit
+//! code. Their stderr, including the panic message, is always compared against
+//! a _*.run.stderr_ file.
+//!
+//! Test cases that only need to compile, such as ones showing that some usage
+//! of a macro is accepted, can be registered with `t.compile_pass(path)`. They
+//! are never run, so their `main` can be empty, and as long as the same
+//! `TestCases` has no pass or run_fail tests they are only type-checked with
+//! `cargo check`, together with the compile_fail tests, which is much faster
+//! than building and linking every one of them.
+//!
+//! Compiler warnings in pass tests are displayed but do not fail the test by
+//! default. Call `t.snapshot_warnings()` to compare them against an adjacently
+//! named _*.stderr_ file just like the errors of a compile_fail test, or
+//! `t.deny_warnings()` to fail any pass test that triggers a warning at all.
+//! The same goes for compile_pass tests.
+//!
+//! A test case that hangs, whether in a procedural macro while compiling or in
+//! an infinite loop while running, can be cut short with
+//! `t.timeout(Duration::from_secs(60))` or by setting the environment variable
+//! `TRYBUILD_TIMEOUT=60` (in seconds). The compiler or test case is then
+//! killed along with any processes it spawned, and the test case fails with
+//! `Error::Timeout`. The timeout applies to each test case's executable and to
+//! each test case's build. When the test cases are built together, that build
+//! gets the timeout once per test case. If it runs out of time anyway, each
+//! test case is built again by itself with the timeout, so that only the one
+//! that hangs fails.
+//!
+//! <br>
+//!
+//! # Inline annotations
+//!
+//! As an alternative to snapshotting the compiler's entire rendered output,
+//! `compile_fail_annotated` test cases state their expected diagnostics in
+//! comments inside the test case source, in the style of rustc's own test
+//! suite.
+//!
+//! ```
+//! #[test]
+//! fn ui() {
+//!     let t = trybuild::TestCases::new();
+//!     t.compile_fail_annotated("tests/ui/annotated/*.rs");
+//! }
+//! ```
+//!
+//! ```rust,ignore
+//! fn main() {
+//!     let x: u8 = ""; //~ ERROR mismatched types
+//! }
+//! ```
+//!
+//! An annotation `//~ LEVEL message` refers to the line on which it appears,
+//! `//~^` refers to the line above (with one `^` per line), and `//~|` refers
+//! to the same line as the previous annotation. The level is one of `ERROR`,
+//! `WARN`, `HELP` or `NOTE`, and the message needs to be a substring of the
+//! compiler's message. Every error must be annotated; warnings, help and notes
+//! are only checked exhaustively if the test case contains at least one
+//! annotation of that level.
+//!
+//! In a directory test case, every _*.rs_ file can have annotations. An error
+//! inside a macro defined elsewhere is expected on the line that invokes the
+//! macro. Errors that point outside the test case altogether cannot be
+//! annotated, and fail the test case.
+//!
+//! This kind of test does not break every time the compiler changes how it
+//! renders a diagnostic, at the cost of not checking the rendering.
+//!
+//! In between the two, calling `t.json_diagnostics()` snapshots the structured
+//! form of each compile_fail test's diagnostics into an adjacent
+//! _*.diagnostics.json_ file in place of the _*.stderr_ file. It keeps the
+//! level, error code, message, location and labels of every diagnostic along
+//! with its child notes and help, with paths normalized the same way as in
+//! _*.stderr_ files, but nothing about how the compiler draws them.
+//!
+//! ```json
+//! [
+//!   {
+//!     "level": "error",
+//!     "code": "E0308",
+//!     "message": "mismatched types",
+//!     "location": "tests/ui/mismatch.rs:2:17",
+//!     "labels": [
+//!       {
+//!         "location": "tests/ui/mismatch.rs:2:17",
+//!         "label": "expected `u8`, found `&str`"
+//!       },
+//!       {
+//!         "location": "tests/ui/mismatch.rs:2:12",
+//!         "label": "expected due to this"
+//!       }
+//!     ]
+//!   }
+//! ]
+//! ```
+//!
+//! Sometimes all that matters is which errors a test case fails with, not what
+//! they say. `compile_fail_with_codes` test cases have no snapshot file at all
+//! and only check the codes of the errors the compiler emits.
+//!
+//! ```
+//! #[test]
+//! fn ui() {
+//!     let t = trybuild::TestCases::new();
+//!     t.compile_fail_with_codes("tests/ui/not-send.rs", ["E0277"]);
+//!     t.compile_fail_with_codes("tests/ui/unresolved.rs", &["E0425", "E0599"]);
+//! }
+//! ```
+//!
+//! The test case passes if the set of distinct codes is exactly the given one,
+//! in any order. Errors that come without a code, such as from `compile_error!`,
+//! count as the code `uncoded`, so the test case fails on them unless `uncoded`
+//! is among the expected codes. Suites that also run on an old compiler can
+//! register the same files this way for that compiler and with `compile_fail`
+//! for the latest one.
+//!
+//! The codes can also be given by the test case itself, in an `//@ error-codes`
+//! directive, which is handy when registering a whole directory at once. They
+//! are expected in addition to any passed to `compile_fail_with_codes`, or on
+//! their own with `compile_fail_with_directive_codes`.
+//!
+//! ```rust,ignore
+//! // tests/ui/codes/unresolved.rs
+//!
+//! //@ error-codes: E0425, E0599
+//!
+//! fn main() {
+//!     let _ = undefined;
+//!     0u8.frobnicate();
+//! }
+//! ```
+//!
+//! ```
+//! #[test]
+//! fn ui() {
+//!     let t = trybuild::TestCases::new();
+//!     t.compile_fail_with_directive_codes("tests/ui/codes/*.rs");
+//! }
+//! ```
+//!
+//! <br>
+//!
+//! # Macro expansion
+//!
+//! To lock down exactly what a procedural macro expands to, `expand` test
+//! cases are compiled with `-Zunpretty=expanded` and the expanded source is
+//! compared against an adjacent _*.expanded.rs_ file, which is created and
+//! updated in the same way as _*.stderr_ files. Nightly-only as the flag is,
+//! this works on a stable compiler too, by setting `RUSTC_BOOTSTRAP=1` for the
+//! test case's compilation.
+//!
+//! ```
+//! #[test]
+//! fn expand() {
+//!     let t = trybuild::TestCases::new();
+//!     t.expand("tests/expand/*.rs");
+//! }
+//! ```
+//!
+//! Globs skip files ending in _.expanded.rs_, so the snapshots can live next to
+//! the test cases.
+//!
+//! <br>
+//!
+//! # Inspecting results
+//!
+//! By default the test cases run when the `TestCases` is dropped, and any
+//! failure is reported by panicking. Tools that want to look at the results
+//! themselves, such as an xtask, can instead call `run()` which consumes the
+//! `TestCases` and returns a `Report` describing the outcome, normalized
+//! compiler output, and duration of every test case.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), trybuild::Error> {
+//! let t = trybuild::TestCases::new();
+//! t.compile_fail("tests/ui/*.rs");
+//! let report = t.run()?;
+//! for test in &report.tests {
+//!     if let trybuild::Outcome::Failed(err) = &test.outcome {
+//!         eprintln!("{}: {}", test.path.display(), err);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! For CI dashboards, setting `TRYBUILD_REPORT=junit:path/to/{name}.xml` or
+//! `TRYBUILD_REPORT=json:path/to/{name}.json` additionally writes the results
+//! to a file, with one record per test case containing its status, duration,
+//! and for failures the expected and actual output. The `{name}` in the path is
+//! required, and is replaced by the name of the #[test] function so that
+//! several such functions do not overwrite each other's report.
+//!
+//! <br>
+//!
+//! # Custom test harness
+//!
+//! Within a #[test] function, all the test cases together show up as a single
+//! test to `cargo test` and cargo-nextest. Alternatively, a test target can
+//! opt out of libtest and let trybuild act as the test harness, which makes
+//! every test case individually listed, filtered and reported, using libtest's
+//! command line options such as `--list`, `--exact` and `--skip`.
+//!
+//! ```toml
+//! [[test]]
+//! name = "ui"
+//! harness = false
+//! ```
+//!
+//! ```no_run
+//! // tests/ui.rs
+//!
+//! fn main() {
+//!     trybuild::harness(|t| {
+//!         t.pass("tests/ui/pass/*.rs");
+//!         t.compile_fail("tests/ui/fail/*.rs");
+//!     });
+//! }
+//! ```
+//!
+//! The name of each test is its path, as in `cargo test --test ui --
+//! tests/ui/fail/tuple.rs --exact`.
+//!
+//! <br>
+//!
+//! # Directives
+//!
+//! Comments starting with `//@` at the top of a test case configure how that
+//! one test case is built. Directives are only read up to the first line that
+//! is neither blank nor a `//@` comment.
+//!
+//! - `//@ edition: 2018` compiles the test case in a different edition than
+//!   the crate under test.
+//! - `//@ compile-flags: --cfg=foo -Zunstable-options` passes extra flags to
+//!   rustc for the test case, but not for its dependencies.
+//! - `//@ rustc-env: VAR=value` sets an environment variable while compiling
+//!   the test case, for `env!` to pick up.
+//! - `//@ features: serde, std` enables features of the crate under test in
+//!   addition to the ones enabled for the rest of the test cases.
+//! - `//@ ignore` or `//@ ignore: reason` skips the test case, reporting it as
+//!   ignored. Under the [custom test harness](#custom-test-harness), `--ignored`
+//!   and `--include-ignored` run it anyway.
+//! - `//@ only-nightly` skips the test case unless the compiler is a nightly.
+//! - `//@ min-rustc: 1.78` skips the test case when compiling with an older
+//!   compiler, for diagnostics that changed or only exist in newer versions.
+//! - `//@ check-spans` checks where the test case's errors point, as described
+//!   under [Compile-fail tests](#compile-fail-tests).
+//!
+//! `//@ aux-crate: helper=aux/helper.rs` compiles _aux/helper.rs_, relative to
+//! the directory containing the test case, as a library crate named `helper`
+//! that the test case can use, for testing behavior that only shows up across
+//! crate boundaries such as `#[non_exhaustive]` or macro hygiene. The aux crate
+//! has access to the same dependencies as the test cases, including the crate
+//! under test, and is not visible to any other test case. An aux crate whose
+//! source contains `//@ proc-macro` is compiled as a procedural macro crate.
+//!
+//! ```rust,ignore
+//! // tests/ui/non-exhaustive.rs
+//!
+//! //@ aux-crate: helper=aux/helper.rs
+//!
+//! fn main() {
+//!     let _ = helper::Config { verbose: true };
+//! }
+//! ```
+//!
+//! Test cases with compile-flags, rustc-env, features or aux-crate directives
+//! are compiled one at a time, separately from the rest.
+//!
+//! `//@ run-rustfix` checks that the compiler's suggestions actually fix the
+//! code, as in rustc's own test suite. The suggestions that the compiler marks
+//! as machine-applicable are applied to a copy of the test case, which is
+//! compared against an adjacent _*.fixed_ file, created and updated in the same
+//! way as _*.stderr_ files, and then compiled to make sure it builds. Use
+//! `//@ run-rustfix: no-build` to only compare against the _*.fixed_ file. For
+//! a test case that is a directory, suggestions are applied to its main.rs
+//! only, and `no-build` is needed if main.rs declares modules.
+//!
 //! <br>
 //!
 //! # Details
@@ -128,6 +437,57 @@
 //! output directly in place. You'll want to check `git diff` afterward to be
 //! sure the compiler's output is what you had in mind.
 //!
+//! To look through several changed snapshots one at a time instead, run with
+//! `TRYBUILD=review`. Wherever a snapshot does not match, the actual output is
+//! then written next to it with a _.new_ suffix, for example
+//! _tests/ui/x.stderr.new_, and the test still fails. Afterward, calling
+//! `trybuild::review("tests/ui")` from an xtask or a small binary walks through
+//! these pending files, showing each one as a diff against the current snapshot,
+//! and lets you accept it (replacing the snapshot) or reject it (deleting the
+//! pending file). The _.new_ files are not meant to be committed; add `*.new` to
+//! your .gitignore if you use this.
+//!
+//! Snapshot files that no longer belong to any test case, for example after a
+//! test case matched by a glob was renamed, are pointed out in a warning at the
+//! start of the run. Run with `TRYBUILD=prune` to delete them.
+//!
+//! Trybuild normalizes the compiler's output before comparing it, for example
+//! by replacing absolute paths with relative ones, so that the saved files are
+//! portable across machines. Output containing other unstable text, such as
+//! hashes or version numbers, can be normalized further through
+//! `t.normalize(from, to)` to replace a literal string, or
+//! `t.normalize_with(|output| ...)` for arbitrary processing. These are applied
+//! after the built-in normalizations, both for the comparison and to the output
+//! that gets written.
+//!
+//! Text you don't care to pin down can also be left out of a _*.stderr_ file by
+//! hand. A line consisting of just `...` matches any number of lines, and `[..]`
+//! within a line matches any part of that line, the same as in the tests of
+//! Cargo itself. This is handy for a long list of "the following other types
+//! implement trait" lines.
+//!
+//! ```console
+//! error[E0277]: cannot add `Thing` to `{integer}`
+//!  --> tests/ui/add.rs:[..]
+//! ...
+//!   = help: the following other types implement trait `Add<Rhs>`:
+//! ...
+//! ```
+//!
+//! Wildcards that still match survive `TRYBUILD=overwrite`, which only rewrites
+//! the lines that changed. Keep in mind that a `...` line that the compiler
+//! prints itself, where it skips over source lines, acts as a wildcard too.
+//!
+//! With `TRYBUILD_CACHE=1` in the environment, test cases that passed are
+//! remembered in _target/tests/trybuild/\<crate\>/cache.json_, and are reported
+//! as "ok (cached)" without being compiled again as long as neither the files
+//! the compiler read for the test case (including modules and `include!`d
+//! files), its snapshots, the crate under test, its dependencies, nor the
+//! compiler have changed since. Pass and run_fail tests are never cached,
+//! because they are run every time. Nothing is cached when running with
+//! `TRYBUILD=overwrite` or when using `normalize_with`; delete the cache file to
+//! force everything to be rebuilt.
+//!
 //! <br>
 //!
 //! # What to test
@@ -244,6 +604,7 @@
     clippy::module_inception,
     clippy::module_name_repetitions,
     clippy::must_use_candidate,
+    clippy::needless_doctest_main,
     clippy::needless_lifetimes,
     clippy::needless_pass_by_value,
     clippy::non_ascii_literal,
@@ -267,26 +628,44 @@ mod term;
 #[macro_use]
 mod path;
 
+mod annotations;
+mod cache;
 mod cargo;
 mod dependencies;
+mod diagnostic;
 mod diff;
+mod directives;
 mod directory;
 mod env;
 mod error;
 mod expand;
 mod features;
 mod flock;
+mod harness;
 mod inherit;
 mod manifest;
 mod message;
 mod normalize;
+mod orphans;
+mod process;
+mod report;
+mod review;
 mod run;
+mod rustc;
+mod rustfix;
 mod rustflags;
+mod spans;
+mod wildcard;
+
+pub use crate::error::Error;
+pub use crate::report::{Outcome, Report, TestReport};
 
 use std::cell::RefCell;
 use std::panic::RefUnwindSafe;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct TestCases {
@@ -296,41 +675,235 @@ pub struct TestCases {
 #[derive(Debug)]
 struct Runner {
     tests: Vec<Test>,
+    snapshot_output: bool,
+    snapshot_warnings: bool,
+    deny_warnings: bool,
+    json_diagnostics: bool,
+    check_spans: bool,
+    normalizations: Vec<normalize::Custom>,
+    finished: bool,
+    harness: Option<harness::Args>,
+    timeout: Option<Duration>,
 }
 
 #[derive(Clone, Debug)]
 struct Test {
     path: PathBuf,
     expected: Expected,
+    // Required by run_fail_with_code.
+    exit_code: Option<i32>,
+    // Required by compile_fail_with_codes.
+    codes: Vec<String>,
+}
+
+impl Test {
+    fn new(path: &Path, expected: Expected) -> Self {
+        Test {
+            path: path.to_owned(),
+            expected,
+            exit_code: None,
+            codes: Vec::new(),
+        }
+    }
 }
 
+/// The kind of test case, corresponding to the `TestCases` method through which
+/// it was registered.
 #[derive(Copy, Clone, Debug)]
-enum Expected {
+#[non_exhaustive]
+pub enum Expected {
+    /// [`TestCases::pass`]
     Pass,
+    /// [`TestCases::compile_pass`]
+    CompilePass,
+    /// [`TestCases::compile_fail`]
     CompileFail,
+    /// [`TestCases::compile_fail_build`]
+    CompileFailBuild,
+    /// [`TestCases::compile_fail_annotated`]
+    CompileFailAnnotated,
+    /// [`TestCases::compile_fail_with_codes`]
+    CompileFailWithCodes,
+    /// [`TestCases::run_fail`] or [`TestCases::run_fail_with_code`]
+    RunFail,
+    /// [`TestCases::expand`]
+    Expand,
+}
+
+impl Expected {
+    // Name of the TestCases method that registers this kind of test case.
+    fn method(self) -> &'static str {
+        match self {
+            Expected::Pass => "pass",
+            Expected::CompilePass => "compile_pass",
+            Expected::CompileFail => "compile_fail",
+            Expected::CompileFailBuild => "compile_fail_build",
+            Expected::CompileFailAnnotated => "compile_fail_annotated",
+            Expected::CompileFailWithCodes => "compile_fail_with_codes",
+            Expected::RunFail => "run_fail",
+            Expected::Expand => "expand",
+        }
+    }
 }
 
 impl TestCases {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         TestCases {
-            runner: RefCell::new(Runner { tests: Vec::new() }),
+            runner: RefCell::new(Runner {
+                tests: Vec::new(),
+                snapshot_output: false,
+                snapshot_warnings: false,
+                deny_warnings: false,
+                json_diagnostics: false,
+                check_spans: false,
+                normalizations: Vec::new(),
+                finished: false,
+                harness: None,
+                timeout: None,
+            }),
         }
     }
 
     pub fn pass<P: AsRef<Path>>(&self, path: P) {
-        self.runner.borrow_mut().tests.push(Test {
-            path: path.as_ref().to_owned(),
-            expected: Expected::Pass,
-        });
+        let test = Test::new(path.as_ref(), Expected::Pass);
+        self.runner.borrow_mut().tests.push(test);
+    }
+
+    pub fn compile_pass<P: AsRef<Path>>(&self, path: P) {
+        let test = Test::new(path.as_ref(), Expected::CompilePass);
+        self.runner.borrow_mut().tests.push(test);
+    }
+
+    pub fn run_fail<P: AsRef<Path>>(&self, path: P) {
+        let test = Test::new(path.as_ref(), Expected::RunFail);
+        self.runner.borrow_mut().tests.push(test);
+    }
+
+    pub fn run_fail_with_code<P: AsRef<Path>>(&self, path: P, code: i32) {
+        let test = Test {
+            exit_code: Some(code),
+            ..Test::new(path.as_ref(), Expected::RunFail)
+        };
+        self.runner.borrow_mut().tests.push(test);
     }
 
     pub fn compile_fail<P: AsRef<Path>>(&self, path: P) {
-        self.runner.borrow_mut().tests.push(Test {
-            path: path.as_ref().to_owned(),
-            expected: Expected::CompileFail,
-        });
+        let test = Test::new(path.as_ref(), Expected::CompileFail);
+        self.runner.borrow_mut().tests.push(test);
     }
+
+    pub fn compile_fail_build<P: AsRef<Path>>(&self, path: P) {
+        let test = Test::new(path.as_ref(), Expected::CompileFailBuild);
+        self.runner.borrow_mut().tests.push(test);
+    }
+
+    pub fn compile_fail_with_codes<P: AsRef<Path>>(
+        &self,
+        path: P,
+        codes: impl IntoIterator<Item = impl AsRef<str>>,
+    ) {
+        let test = Test {
+            codes: codes
+                .into_iter()
+                .map(|code| code.as_ref().to_owned())
+                .collect(),
+            ..Test::new(path.as_ref(), Expected::CompileFailWithCodes)
+        };
+        self.runner.borrow_mut().tests.push(test);
+    }
+
+    pub fn compile_fail_with_directive_codes<P: AsRef<Path>>(&self, path: P) {
+        let test = Test::new(path.as_ref(), Expected::CompileFailWithCodes);
+        self.runner.borrow_mut().tests.push(test);
+    }
+
+    pub fn expand<P: AsRef<Path>>(&self, path: P) {
+        let test = Test::new(path.as_ref(), Expected::Expand);
+        self.runner.borrow_mut().tests.push(test);
+    }
+
+    pub fn compile_fail_annotated<P: AsRef<Path>>(&self, path: P) {
+        let test = Test::new(path.as_ref(), Expected::CompileFailAnnotated);
+        self.runner.borrow_mut().tests.push(test);
+    }
+
+    pub fn snapshot_output(&self) {
+        self.runner.borrow_mut().snapshot_output = true;
+    }
+
+    pub fn snapshot_warnings(&self) {
+        self.runner.borrow_mut().snapshot_warnings = true;
+    }
+
+    pub fn deny_warnings(&self) {
+        self.runner.borrow_mut().deny_warnings = true;
+    }
+
+    pub fn json_diagnostics(&self) {
+        self.runner.borrow_mut().json_diagnostics = true;
+    }
+
+    pub fn check_spans(&self) {
+        self.runner.borrow_mut().check_spans = true;
+    }
+
+    /// Replaces every occurrence of the literal string `from` with `to` in the
+    /// compiler's output, after the built-in normalizations. No characters in
+    /// `from` have a special meaning.
+    pub fn normalize<S: AsRef<str>, R: AsRef<str>>(&self, from: S, to: R) {
+        let from = from.as_ref().to_owned();
+        let to = to.as_ref().to_owned();
+        let normalization = normalize::Custom::Replace(from, to);
+        self.runner.borrow_mut().normalizations.push(normalization);
+    }
+
+    pub fn normalize_with<F>(&self, normalize: F)
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        let normalization = normalize::Custom::Function(Arc::new(normalize));
+        self.runner.borrow_mut().normalizations.push(normalization);
+    }
+
+    pub fn timeout(&self, timeout: Duration) {
+        self.runner.borrow_mut().timeout = Some(timeout);
+    }
+
+    pub fn run(self) -> Result<Report, Error> {
+        self.runner.borrow_mut().run()
+    }
+}
+
+pub fn harness<F>(f: F)
+where
+    F: FnOnce(&TestCases),
+{
+    let mut t = TestCases::new();
+    f(&t);
+    let args = harness::Args::from_env();
+    let success = harness::run(t.runner.get_mut(), args);
+    drop(t);
+    if !success {
+        std::process::exit(101);
+    }
+}
+
+/// Walks through the pending snapshots left under `dir` by a run with
+/// `TRYBUILD=review`, asking on the terminal whether to accept or reject each
+/// one.
+///
+/// Accepting replaces the snapshot with the pending file, and rejecting deletes
+/// the pending file. Nothing happens if stdin is not a terminal.
+///
+/// ```no_run
+/// fn main() -> Result<(), trybuild::Error> {
+///     trybuild::review("tests/ui")
+/// }
+/// ```
+pub fn review<P: AsRef<Path>>(dir: P) -> Result<(), Error> {
+    let pending = review::find_pending(dir.as_ref())?;
+    review::review(&pending)
 }
 
 impl RefUnwindSafe for TestCases {}
@@ -338,8 +911,9 @@ impl RefUnwindSafe for TestCases {}
 #[doc(hidden)]
 impl Drop for TestCases {
     fn drop(&mut self) {
-        if !thread::panicking() {
-            self.runner.borrow_mut().run();
+        let runner = self.runner.get_mut();
+        if !thread::panicking() && !runner.finished {
+            runner.run_and_assert();
         }
     }
 }
@@ -38,7 +38,7 @@ pub(crate) struct Package {
     pub publish: bool,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub(crate) enum Edition {
     #[default]
     #[serde(rename = "2015")]
@@ -51,7 +51,18 @@ pub(crate) enum Edition {
     E2024,
 }
 
-#[derive(Serialize, Debug)]
+impl Edition {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
 pub(crate) struct Bin {
     pub name: Name,
     pub path: PathBuf,
@@ -60,8 +71,33 @@ pub(crate) struct Bin {
 #[derive(Serialize, Clone, Debug)]
 pub(crate) struct Name(pub String);
 
+impl Name {
+    // Bin for the source of a `//@ run-rustfix` test case with the compiler's
+    // suggestions applied.
+    pub fn fixed(&self) -> Name {
+        Name(format!("{}_fixed", self.0))
+    }
+}
+
+// Package generated for an aux crate declared by a test case.
+#[derive(Serialize, Debug)]
+pub(crate) struct AuxManifest {
+    pub package: Package,
+    pub lib: Lib,
+    pub dependencies: Map<String, Dependency>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct Lib {
+    pub path: PathBuf,
+    #[serde(rename = "proc-macro")]
+    pub proc_macro: bool,
+}
+
 #[derive(Serialize, Debug)]
 pub(crate) struct Workspace {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<String>,
     #[serde(skip_serializing_if = "Map::is_empty")]
     pub dependencies: Map<String, Dependency>,
 }
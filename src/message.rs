@@ -1,8 +1,12 @@
+use crate::annotations::Mismatch;
 use crate::diff::{Diff, Render};
 use crate::error::Error;
+use crate::run::UNCODED;
+use crate::spans::BadSpan;
 use crate::{normalize, term, Expected, Test};
 use std::env;
-use std::path::Path;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
 use std::process::Output;
 use termcolor::Color::{self, *};
 
@@ -13,7 +17,7 @@ pub(crate) enum Level {
 
 pub(crate) use self::Level::*;
 
-pub(crate) fn prepare_fail(err: Error) {
+pub(crate) fn prepare_fail(err: &Error) {
     if err.already_printed() {
         return;
     }
@@ -25,7 +29,7 @@ pub(crate) fn prepare_fail(err: Error) {
     println!();
 }
 
-pub(crate) fn test_fail(err: Error) {
+pub(crate) fn test_fail(err: &Error) {
     if err.already_printed() {
         return;
     }
@@ -44,12 +48,66 @@ pub(crate) fn no_tests_enabled() {
     term::reset();
 }
 
+pub(crate) fn empty_glob(test: &Test) {
+    term::bold_color(Yellow);
+    print!("WARNING");
+    term::reset();
+    println!(
+        ": {} pattern `{}` does not match any files",
+        test.expected.method(),
+        test.path.display(),
+    );
+    println!();
+}
+
+pub(crate) fn batch_timed_out() {
+    term::bold_color(Yellow);
+    print!("WARNING");
+    term::reset();
+    println!(": building the test cases together ran out of time, building each one by itself");
+    println!();
+}
+
+pub(crate) fn orphaned_snapshots(paths: &[PathBuf], pruned: bool) {
+    term::bold_color(Yellow);
+    print!("WARNING");
+    term::reset();
+    if pruned {
+        println!(": deleted the following files which do not belong to any test case:");
+    } else {
+        println!(": the following files do not belong to any test case:");
+    }
+    for path in paths {
+        println!("    {}", path.display());
+    }
+    if !pruned {
+        println!("Rerun with the environment variable TRYBUILD=prune to delete them.");
+    }
+    println!();
+}
+
 pub(crate) fn ok() {
     term::color(Green);
     println!("ok");
     term::reset();
 }
 
+pub(crate) fn ignored(reason: &str) {
+    term::color(Yellow);
+    if reason.is_empty() {
+        println!("ignored");
+    } else {
+        println!("ignored, {}", reason);
+    }
+    term::reset();
+}
+
+pub(crate) fn cached() {
+    term::color(Green);
+    println!("ok (cached)");
+    term::reset();
+}
+
 pub(crate) fn begin_test(test: &Test, show_expected: bool) {
     let display_name = test.path.as_os_str().to_string_lossy();
 
@@ -61,7 +119,15 @@ pub(crate) fn begin_test(test: &Test, show_expected: bool) {
     if show_expected {
         match test.expected {
             Expected::Pass => print!(" [should pass]"),
-            Expected::CompileFail => print!(" [should fail to compile]"),
+            Expected::CompilePass => print!(" [should compile]"),
+            Expected::RunFail => print!(" [should fail at runtime]"),
+            Expected::CompileFail
+            | Expected::CompileFailBuild
+            | Expected::CompileFailAnnotated
+            | Expected::CompileFailWithCodes => {
+                print!(" [should fail to compile]");
+            }
+            Expected::Expand => print!(" [should expand]"),
         }
     }
 
@@ -75,6 +141,19 @@ pub(crate) fn failed_to_build(stderr: &str) {
     println!();
 }
 
+pub(crate) fn fixed_failed_to_build(fixed_path: &Path, stderr: &str) {
+    term::bold_color(Red);
+    println!("error");
+    term::color(Red);
+    println!(
+        "Test case with the compiler's suggestions applied, as in {}, failed to compile.",
+        fixed_path.display(),
+    );
+    term::reset();
+    snippet(Red, stderr);
+    println!();
+}
+
 pub(crate) fn should_not_have_compiled() {
     term::bold_color(Red);
     println!("error");
@@ -84,6 +163,45 @@ pub(crate) fn should_not_have_compiled() {
     println!();
 }
 
+pub(crate) fn unexpected_warnings(warnings: &str) {
+    term::bold_color(Red);
+    println!("error");
+    term::color(Red);
+    println!("Expected test case to compile without warnings.");
+    term::reset();
+    println!();
+    self::warnings(warnings);
+}
+
+pub(crate) fn should_not_have_run_successfully(warnings: &str, output: &Output) {
+    term::bold_color(Red);
+    println!("error");
+    term::color(Red);
+    println!("Expected test case to fail at runtime, but it ran successfully.");
+    term::reset();
+    println!();
+    runtime_output(Red, warnings, output);
+}
+
+pub(crate) fn wrong_exit_code(expected: i32, actual: Option<i32>, warnings: &str, output: &Output) {
+    term::bold_color(Red);
+    println!("error");
+    term::color(Red);
+    match actual {
+        Some(actual) => println!(
+            "Expected test case to exit with code {}, but it exited with code {}.",
+            expected, actual,
+        ),
+        None => println!(
+            "Expected test case to exit with code {}, but it was terminated by a signal.",
+            expected,
+        ),
+    }
+    term::reset();
+    println!();
+    runtime_output(Red, warnings, output);
+}
+
 pub(crate) fn write_stderr_wip(wip_path: &Path, stderr_path: &Path, stderr: &str) {
     let wip_path = wip_path.to_string_lossy();
     let stderr_path = stderr_path.to_string_lossy();
@@ -115,11 +233,75 @@ pub(crate) fn overwrite_stderr(stderr_path: &Path, stderr: &str) {
     println!();
 }
 
-pub(crate) fn mismatch(expected: &str, actual: &str) {
+pub(crate) fn mismatch(expected: &str, actual: &str, pending_path: Option<&Path>) {
     term::bold_color(Red);
     println!("mismatch");
     term::reset();
     println!();
+    expected_actual(expected, actual);
+    print!("note: If the ");
+    term::color(Red);
+    print!("actual output");
+    term::reset();
+    if let Some(pending_path) = pending_path {
+        println!(" is the correct output, it was written to");
+        println!(
+            "      {} for trybuild::review to accept",
+            pending_path.display(),
+        );
+    } else {
+        println!(" is the correct output you can bless it by rerunning");
+        println!("      your test with the environment variable TRYBUILD=overwrite");
+    }
+    println!();
+}
+
+pub(crate) fn review_snapshot(
+    snapshot_path: &Path,
+    index: usize,
+    count: usize,
+    expected: &str,
+    actual: &str,
+) {
+    term::bold();
+    print!("review {}", snapshot_path.display());
+    term::reset();
+    println!(" ({} of {})", index, count);
+    println!();
+    expected_actual(expected, actual);
+}
+
+pub(crate) fn review_prompt() {
+    print!("accept (a), reject (r), or skip (s)? ");
+    let _ = term::lock().flush();
+}
+
+pub(crate) fn review_accepted(snapshot_path: &Path) {
+    term::color(Green);
+    println!("accepted {}", snapshot_path.display());
+    term::reset();
+    println!();
+}
+
+pub(crate) fn review_rejected(snapshot_path: &Path) {
+    term::color(Yellow);
+    println!("rejected {}", snapshot_path.display());
+    term::reset();
+    println!();
+}
+
+pub(crate) fn review_not_interactive(count: usize) {
+    term::bold_color(Yellow);
+    print!("WARNING");
+    term::reset();
+    println!(
+        ": not reviewing {} pending snapshot files because stdin is not a terminal",
+        count,
+    );
+    println!();
+}
+
+fn expected_actual(expected: &str, actual: &str) {
     let diff = if env::var_os("TERM").is_none_or(|term| term == "dumb") {
         // No diff in dumb terminal or when TERM is unset.
         None
@@ -133,15 +315,130 @@ pub(crate) fn mismatch(expected: &str, actual: &str) {
     term::bold_color(Red);
     println!("ACTUAL OUTPUT:");
     snippet_diff(Red, actual, diff.as_ref());
-    print!("note: If the ");
+}
+
+pub(crate) fn annotations_mismatch(mismatch: &Mismatch, actual: &str) {
+    term::bold_color(Red);
+    println!("mismatch");
+    term::reset();
+    println!();
+
+    if !mismatch.not_found.is_empty() {
+        term::bold_color(Blue);
+        println!("EXPECTED BUT NOT FOUND:");
+        term::color(Blue);
+        for annotation in &mismatch.not_found {
+            println!(
+                "{}:{}: {} {}",
+                annotation.file.display(),
+                annotation.line,
+                annotation.kind,
+                annotation.message,
+            );
+        }
+        term::reset();
+        println!();
+    }
+
+    if !mismatch.unexpected.is_empty() {
+        term::bold_color(Red);
+        println!("UNEXPECTED:");
+        term::color(Red);
+        for actual in &mismatch.unexpected {
+            println!("{}: {} {}", actual.location, actual.kind, actual.message);
+        }
+        term::reset();
+        println!();
+    }
+
+    if !mismatch.outside.is_empty() {
+        term::bold_color(Red);
+        println!("UNEXPECTED OUTSIDE THE TEST CASE:");
+        term::color(Red);
+        for actual in &mismatch.outside {
+            println!("{}: {} {}", actual.location, actual.kind, actual.message);
+        }
+        term::reset();
+        println!("These errors are not in any file of the test case, so they cannot be annotated.");
+        println!();
+    }
+
+    term::bold_color(Red);
+    println!("ACTUAL OUTPUT:");
+    snippet(Red, actual);
+    println!();
+}
+
+pub(crate) fn bad_spans(bad_spans: &[BadSpan], stderr: &str) {
+    term::bold_color(Red);
+    println!("error");
     term::color(Red);
-    print!("actual output");
+    println!("Expected every error to point at the offending code.");
+    for bad_span in bad_spans {
+        println!(
+            "{}: `{}` {}",
+            bad_span.location, bad_span.message, bad_span.problem,
+        );
+    }
     term::reset();
-    println!(" is the correct output you can bless it by rerunning");
-    println!("      your test with the environment variable TRYBUILD=overwrite");
+    println!();
+
+    term::bold_color(Red);
+    println!("ACTUAL OUTPUT:");
+    snippet(Red, stderr);
     println!();
 }
 
+pub(crate) fn codes_mismatch(expected: &[&str], actual: &[&str], uncoded: usize, stderr: &str) {
+    term::bold_color(Red);
+    println!("mismatch");
+    term::reset();
+    println!();
+
+    term::bold_color(Blue);
+    println!("EXPECTED ERROR CODES:");
+    term::color(Blue);
+    println!("{}", codes_list(expected));
+    term::reset();
+    println!();
+
+    term::bold_color(Red);
+    println!("ACTUAL ERROR CODES:");
+    term::color(Red);
+    println!("{}", codes_list(actual));
+    if uncoded > 0 {
+        println!(
+            "({} {} without a code, counted as `{}`)",
+            uncoded,
+            if uncoded == 1 { "error" } else { "errors" },
+            UNCODED,
+        );
+    }
+    term::reset();
+    println!();
+
+    term::bold_color(Red);
+    println!("ACTUAL OUTPUT:");
+    snippet(Red, stderr);
+    println!();
+}
+
+fn codes_list(codes: &[&str]) -> String {
+    if codes.is_empty() {
+        "(none)".to_owned()
+    } else {
+        codes.join(", ")
+    }
+}
+
+pub(crate) fn compiled(warnings: &str) {
+    ok();
+    if !warnings.is_empty() {
+        println!();
+        self::warnings(warnings);
+    }
+}
+
 pub(crate) fn output(warnings: &str, output: &Output) {
     let success = output.status.success();
     let stdout = normalize::trim(&output.stdout);
@@ -166,9 +463,15 @@ pub(crate) fn output(warnings: &str, output: &Output) {
         println!();
     }
 
+    let color = if success { Yellow } else { Red };
+    runtime_output(color, warnings, output);
+}
+
+fn runtime_output(color: Color, warnings: &str, output: &Output) {
     self::warnings(warnings);
 
-    let color = if success { Yellow } else { Red };
+    let stdout = normalize::trim(&output.stdout);
+    let stderr = normalize::trim(&output.stderr);
 
     for (name, content) in &[("STDOUT", stdout), ("STDERR", stderr)] {
         if !content.is_empty() {
@@ -6,8 +6,10 @@ use self::Normalization::*;
 use crate::directory::Directory;
 use crate::run::PathDependency;
 use std::cmp;
+use std::fmt::{self, Debug};
 use std::mem;
 use std::path::Path;
+use std::sync::Arc;
 
 #[derive(Copy, Clone)]
 pub(crate) struct Context<'a> {
@@ -17,6 +19,37 @@ pub(crate) struct Context<'a> {
     pub input_file: &'a Path,
     pub target_dir: &'a Directory,
     pub path_dependencies: &'a [PathDependency],
+    pub custom: &'a [Custom],
+}
+
+/// Normalization registered by the user through `TestCases::normalize` or
+/// `TestCases::normalize_with`, applied after all the built-in ones.
+#[derive(Clone)]
+pub(crate) enum Custom {
+    Replace(String, String),
+    Function(Arc<dyn Fn(&str) -> String + Send + Sync>),
+}
+
+impl Custom {
+    fn apply(&self, output: &str) -> String {
+        match self {
+            Custom::Replace(from, to) => output.replace(from, to),
+            Custom::Function(f) => f(output),
+        }
+    }
+}
+
+impl Debug for Custom {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Custom::Replace(from, to) => formatter
+                .debug_tuple("Replace")
+                .field(from)
+                .field(to)
+                .finish(),
+            Custom::Function(_) => formatter.write_str("Function"),
+        }
+    }
 }
 
 macro_rules! normalizations {
@@ -87,6 +120,10 @@ pub(crate) fn diagnostics(output: &str, context: Context) -> Variations {
         result.variations[i] = apply(&output, *normalization, context);
     }
 
+    for variation in &mut result.variations {
+        *variation = apply_custom(variation, context);
+    }
+
     result
 }
 
@@ -127,6 +164,96 @@ pub(crate) fn trim<S: AsRef<[u8]>>(output: S) -> String {
     normalized
 }
 
+/// Normalizes the output of `-Zunpretty=expanded` for comparison against an
+/// *.expanded.rs snapshot.
+pub(crate) fn expanded(output: &[u8], context: Context) -> String {
+    let output = String::from_utf8_lossy(output).replace("\r\n", "\n");
+
+    let mut normalized = String::new();
+    for line in output.lines() {
+        let mut line = replace_case_insensitive(line, &context.source_dir.to_string_lossy(), "");
+        line = replace_case_insensitive(&line, &context.workspace.to_string_lossy(), "$WORKSPACE/");
+        normalized.push_str(&line);
+        normalized.push('\n');
+    }
+
+    apply_custom(&trim(normalized), context)
+}
+
+/// Normalizes a *.diagnostics.json snapshot, whose messages and locations have
+/// already been normalized one at a time by `message` and `location`.
+pub(crate) fn diagnostics_json(json: &str, context: Context) -> String {
+    apply_custom(&trim(json), context)
+}
+
+/// The location of a span as it appears after `-->` in rendered diagnostics,
+/// normalized the same way, including erasing line and column numbers outside
+/// of the input file.
+pub(crate) fn location(file_name: &Path, line: usize, column: usize, context: Context) -> String {
+    let arrow = format!("--> {}:{}:{}", file_name.display(), line, column);
+    let preferred = *Normalization::ALL.last().unwrap();
+    let normalized = apply(&arrow, preferred, context);
+    let normalized = normalized.trim_end();
+    normalized
+        .strip_prefix("--> ")
+        .unwrap_or(normalized)
+        .to_owned()
+}
+
+/// Paths mentioned in the text of a diagnostic message or label.
+pub(crate) fn message(text: &str, context: Context) -> String {
+    let text = replace_case_insensitive(text, &context.source_dir.to_string_lossy(), "");
+    replace_case_insensitive(&text, &context.workspace.to_string_lossy(), "$WORKSPACE/")
+}
+
+/// Normalizes the stdout or stderr of a test case's executable for comparison
+/// against a snapshot. Unlike compiler diagnostics there is just one variation.
+pub(crate) fn runtime_output(output: &[u8], context: Context) -> String {
+    let output = String::from_utf8_lossy(output).replace("\r\n", "\n");
+
+    let mut normalized = String::new();
+    for line in output.lines() {
+        let mut line = line.to_owned();
+
+        // thread 'main' (12345) panicked at src/main.rs:2:5:
+        // thread 'main' panicked at src/main.rs:2:5:
+        if let Some(rest) = line.strip_prefix("thread '") {
+            if let Some(end_of_name) = rest.find("' (") {
+                let id_start = "thread '".len() + end_of_name + "' (".len();
+                let digits = line[id_start..]
+                    .bytes()
+                    .take_while(u8::is_ascii_digit)
+                    .count();
+                if digits > 0 && line[id_start + digits..].starts_with(") ") {
+                    line.replace_range(id_start - 2..id_start + digits + 1, "");
+                }
+            }
+        }
+
+        line = replace_case_insensitive(&line, &context.source_dir.to_string_lossy(), "");
+        line = replace_case_insensitive(&line, &context.workspace.to_string_lossy(), "$WORKSPACE/");
+        if normalized.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+        normalized.push_str(&line);
+        normalized.push('\n');
+    }
+
+    apply_custom(&trim(normalized), context)
+}
+
+fn apply_custom(output: &str, context: Context) -> String {
+    if context.custom.is_empty() {
+        return output.to_owned();
+    }
+
+    let mut normalized = output.to_owned();
+    for custom in context.custom {
+        normalized = custom.apply(&normalized);
+    }
+    trim(normalized)
+}
+
 fn apply(original: &str, normalization: Normalization, context: Context) -> String {
     let mut normalized = String::new();
 
@@ -237,10 +364,11 @@ impl<'a> Filter<'a> {
                         .to_string_lossy()
                         .to_ascii_lowercase()
                         .replace('\\', "/");
-                    if line_lower[i + source_dir_pat.len()..].starts_with(&input_file_pat) {
+                    if is_within_input(&line_lower[i + source_dir_pat.len()..], &input_file_pat) {
                         // Keep line numbers only within the input file (the
-                        // path passed to our `fn compile_fail`. All other
-                        // source files get line numbers erased below.
+                        // path passed to our `fn compile_fail`, or any file
+                        // inside of it if it is a directory). All other source
+                        // files get line numbers erased below.
                         return Some(line);
                     }
                 } else {
@@ -468,6 +596,14 @@ impl<'a> Filter<'a> {
     }
 }
 
+// "tests/ui/dir/mod.rs:3:5" is within input "tests/ui/dir" or "tests/ui/dir/".
+fn is_within_input(path: &str, input_file_pat: &str) -> bool {
+    let Some(rest) = path.strip_prefix(input_file_pat) else {
+        return false;
+    };
+    input_file_pat.ends_with('/') || rest.starts_with([':', '/'])
+}
+
 fn is_ascii_lowercase_hex(s: &str) -> bool {
     s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
 }
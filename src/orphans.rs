@@ -0,0 +1,101 @@
+use crate::expand::ExpandedTest;
+use crate::{Expected, Test};
+use std::collections::BTreeSet as Set;
+use std::path::PathBuf;
+
+pub(crate) const SNAPSHOT_EXTENSIONS: &[&str] = &[
+    "stderr",
+    "run.stderr",
+    "stdout",
+    "expanded.rs",
+    "diagnostics.json",
+    "fixed",
+];
+
+// Snapshot files in the directories covered by the registered globs which do
+// not belong to any of the test cases, typically left behind after a test case
+// was renamed or deleted.
+//
+// For a glob like `tests/ui/*.rs` this looks at `tests/ui/*.stderr` and so on
+// for each of the snapshot extensions.
+pub(crate) fn find(tests: &[Test], expanded: &[ExpandedTest]) -> Vec<PathBuf> {
+    let mut snapshots = Set::new();
+    for e in expanded {
+        for extension in SNAPSHOT_EXTENSIONS {
+            snapshots.insert(e.test.path.with_extension(extension));
+        }
+    }
+
+    let mut orphans = Set::new();
+    for test in tests {
+        let Some(pattern) = test.path.to_str() else {
+            continue;
+        };
+        if !pattern.contains('*') {
+            continue;
+        }
+        let Some(stem) = pattern.strip_suffix(".rs") else {
+            continue;
+        };
+        for extension in SNAPSHOT_EXTENSIONS {
+            let Ok(paths) = glob::glob(&format!("{}.{}", stem, extension)) else {
+                continue;
+            };
+            for path in paths.flatten() {
+                if !snapshots.contains(&path) {
+                    orphans.insert(path);
+                }
+            }
+        }
+    }
+
+    orphans.into_iter().collect()
+}
+
+// Globs of compile_fail and expand test cases that match nothing, most likely
+// due to a typo. These would otherwise silently test nothing.
+pub(crate) fn empty_globs(tests: &[Test]) -> Vec<&Test> {
+    tests
+        .iter()
+        .filter(|test| {
+            matches!(
+                test.expected,
+                Expected::CompileFail
+                    | Expected::CompileFailBuild
+                    | Expected::CompileFailAnnotated
+                    | Expected::CompileFailWithCodes
+                    | Expected::Expand,
+            )
+        })
+        .filter_map(|test| {
+            let pattern = test.path.to_str()?;
+            if !pattern.contains('*') {
+                return None;
+            }
+            let mut paths = glob::glob(pattern).ok()?;
+            paths.next().is_none().then_some(test)
+        })
+        .collect()
+}
+
+#[test]
+fn test_find() {
+    use std::path::Path;
+
+    let compile_fail = |path: &str| Test::new(Path::new(path), Expected::CompileFail);
+    let registered = [compile_fail("tests/ui/compile-fail-*.rs")];
+    let expanded = crate::expand::expand_globs(&[
+        compile_fail("tests/ui/compile-fail-2.rs"),
+        compile_fail("tests/ui/compile-fail-3.rs"),
+    ]);
+    assert_eq!(
+        find(&registered, &expanded),
+        [PathBuf::from("tests/ui/compile-fail-normalize.stderr")],
+    );
+    assert!(empty_globs(&registered).is_empty());
+    let typo = [Test::new(Path::new("tests/ui/typo-*.rs"), Expected::Expand)];
+    let empty = empty_globs(&typo);
+    assert_eq!(empty.len(), 1);
+    assert_eq!(empty[0].path, Path::new("tests/ui/typo-*.rs"));
+    assert_eq!(empty[0].expected.method(), "expand");
+}
@@ -44,6 +44,10 @@ impl CanonicalPath {
             CanonicalPath(path.to_owned())
         }
     }
+
+    pub(crate) fn starts_with(&self, base: &CanonicalPath) -> bool {
+        self.0.starts_with(&base.0)
+    }
 }
 
 #[test]
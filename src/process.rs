@@ -0,0 +1,107 @@
+use std::io::{self, Read};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// Like Command::output, but if the child has not exited by the timeout, kills
+// it along with every process it spawned and returns None.
+pub(crate) fn output(cmd: &mut Command, timeout: Option<Duration>) -> io::Result<Option<Output>> {
+    let Some(timeout) = timeout else {
+        return cmd.output().map(Some);
+    };
+
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    let mut child = cmd.spawn()?;
+    let stdout = read_to_end(child.stdout.take());
+    let stderr = read_to_end(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            kill_tree(&mut child);
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+fn read_to_end(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+// The child stays in the caller's process group, so that Ctrl-C in the terminal
+// reaches it and everything it spawned, such as rustc under cargo. Killing the
+// tree on timeout instead goes by the parent of every process as listed by ps.
+#[cfg(unix)]
+fn kill_tree(child: &mut Child) {
+    let mut pids = vec![child.id()];
+    let ps = Command::new("ps")
+        .arg("-A")
+        .arg("-o")
+        .arg("pid=,ppid=")
+        .stderr(Stdio::null())
+        .output();
+    if let Ok(ps) = ps {
+        let processes: Vec<(u32, u32)> = String::from_utf8_lossy(&ps.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let pid = fields.next()?.parse().ok()?;
+                let ppid = fields.next()?.parse().ok()?;
+                Some((pid, ppid))
+            })
+            .collect();
+        let mut i = 0;
+        while let Some(&parent) = pids.get(i) {
+            let children = processes.iter().filter(|(_pid, ppid)| *ppid == parent);
+            pids.extend(children.map(|(pid, _ppid)| *pid));
+            i += 1;
+        }
+    }
+
+    let _ = Command::new("kill")
+        .arg("-KILL")
+        .arg("--")
+        .args(pids.iter().map(u32::to_string))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    let _ = child.kill();
+}
+
+#[cfg(windows)]
+fn kill_tree(child: &mut Child) {
+    let _ = Command::new("taskkill")
+        .arg("/T")
+        .arg("/F")
+        .arg("/PID")
+        .arg(child.id().to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    let _ = child.kill();
+}
+
+#[cfg(not(any(unix, windows)))]
+fn kill_tree(child: &mut Child) {
+    let _ = child.kill();
+}
@@ -0,0 +1,348 @@
+use crate::env::{ReportFormat, ReportOutput};
+use crate::error::{Error, Result};
+use crate::Expected;
+use serde_derive::Serialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap as Map;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+/// Results of running a set of test cases, returned by
+/// [`TestCases::run`][crate::TestCases::run].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Report {
+    /// One entry per test case, after expanding globs, in the order that the
+    /// test cases were run.
+    pub tests: Vec<TestReport>,
+}
+
+/// Result of an individual test case.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct TestReport {
+    /// Path of the test case, as registered or as matched by a glob.
+    pub path: PathBuf,
+    /// How the test case was registered.
+    pub expected: Expected,
+    pub outcome: Outcome,
+    /// The compiler's output for this test case after normalization, as it
+    /// would be written to a _*.stderr_ file.
+    pub stderr: String,
+    pub duration: Duration,
+    /// Whether the test case passed in an earlier run with the same inputs and
+    /// was not built again, see `TRYBUILD_CACHE`.
+    pub cached: bool,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Outcome {
+    /// The test case behaved as expected.
+    Passed,
+    /// The expected output did not exist yet, and the actual output was written
+    /// to the *wip* directory.
+    CreatedWip,
+    /// The test case did not behave as expected, for example
+    /// [`Error::Mismatch`] or [`Error::ShouldNotHaveCompiled`].
+    Failed(Error),
+    /// The test case was not run, for the given reason, because of an
+    /// `//@ ignore`, `//@ only-nightly` or `//@ min-rustc` directive.
+    Ignored(String),
+}
+
+impl Report {
+    /// Number of test cases with an outcome of [`Outcome::Failed`].
+    pub fn failures(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|test| matches!(test.outcome, Outcome::Failed(_)))
+            .count()
+    }
+
+    /// Number of test cases with an outcome of [`Outcome::CreatedWip`].
+    pub fn created_wip(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|test| matches!(test.outcome, Outcome::CreatedWip))
+            .count()
+    }
+
+    /// Number of test cases with an outcome of [`Outcome::Ignored`].
+    pub fn ignored(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|test| matches!(test.outcome, Outcome::Ignored(_)))
+            .count()
+    }
+}
+
+// Writes the report to the file requested by $TRYBUILD_REPORT, one record per
+// test case. The suite is named after the #[test] function running trybuild,
+// which libtest uses as the name of the thread.
+pub(crate) fn write(
+    output: &ReportOutput,
+    report: &Report,
+    project_name: &str,
+    snapshots: Snapshots,
+) -> Result<()> {
+    let thread = thread::current();
+    let suite = thread.name().unwrap_or(project_name);
+    let path = PathBuf::from(
+        output
+            .path
+            .to_string_lossy()
+            .replace("{name}", &suite.replace("::", "-")),
+    );
+
+    let contents = match output.format {
+        ReportFormat::Junit => junit(suite, report, snapshots),
+        ReportFormat::Json => json(suite, report, snapshots),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| Error::WriteReport(path.clone(), err))?;
+    }
+    fs::write(&path, contents).map_err(|err| Error::WriteReport(path, err))
+}
+
+fn junit(suite: &str, report: &Report, snapshots: Snapshots) -> String {
+    let mut out = String::new();
+    let total: Duration = report.tests.iter().map(|test| test.duration).sum();
+    out += "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+    out += &format!(
+        "<testsuites>\n<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">\n",
+        xml_escape(suite),
+        report.tests.len(),
+        report.failures() + report.created_wip(),
+        report.ignored(),
+        total.as_secs_f64(),
+    );
+
+    for test in &report.tests {
+        out += &format!(
+            "<testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+            xml_escape(suite),
+            xml_escape(&test.path.to_string_lossy()),
+            test.duration.as_secs_f64(),
+        );
+        let message = match &test.outcome {
+            Outcome::Passed => {
+                out += "/>\n";
+                continue;
+            }
+            Outcome::Ignored(reason) => {
+                out += &format!(">\n<skipped message=\"{}\"/>\n", xml_escape(reason));
+                out += "</testcase>\n";
+                continue;
+            }
+            Outcome::CreatedWip => "expected output does not exist yet".to_owned(),
+            Outcome::Failed(err) => err.to_string(),
+        };
+        out += ">\n";
+        out += &format!("<failure message=\"{}\">", xml_escape(&message));
+        for (snapshot_path, expected) in expected_snapshots(test, snapshots) {
+            out += &format!(
+                "EXPECTED ({}):\n{}\n\n",
+                xml_escape(&snapshot_path.to_string_lossy()),
+                xml_escape(&expected),
+            );
+        }
+        out += &format!("ACTUAL OUTPUT:\n{}", xml_escape(&test.stderr));
+        out += "</failure>\n";
+        out += &format!("<system-err>{}</system-err>\n", xml_escape(&test.stderr));
+        out += "</testcase>\n";
+    }
+
+    out += "</testsuite>\n</testsuites>\n";
+    out
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    name: &'a str,
+    tests: Vec<JsonTest<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonTest<'a> {
+    name: Cow<'a, str>,
+    expected: &'static str,
+    status: &'static str,
+    duration: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    snapshots: Map<String, String>,
+    stderr: &'a str,
+}
+
+fn json(suite: &str, report: &Report, snapshots: Snapshots) -> String {
+    let tests = report
+        .tests
+        .iter()
+        .map(|test| {
+            let (status, error) = match &test.outcome {
+                Outcome::Passed => ("passed", None),
+                Outcome::CreatedWip => ("wip", None),
+                Outcome::Failed(err) => ("failed", Some(err.to_string())),
+                Outcome::Ignored(_) => ("ignored", None),
+            };
+            let snapshots = match &test.outcome {
+                Outcome::Passed | Outcome::Ignored(_) => Map::new(),
+                Outcome::CreatedWip | Outcome::Failed(_) => expected_snapshots(test, snapshots)
+                    .into_iter()
+                    .map(|(path, expected)| (path.to_string_lossy().into_owned(), expected))
+                    .collect(),
+            };
+            JsonTest {
+                name: test.path.to_string_lossy(),
+                expected: test.expected.method(),
+                status,
+                duration: test.duration.as_secs_f64(),
+                error,
+                snapshots,
+                stderr: &test.stderr,
+            }
+        })
+        .collect();
+
+    let report = JsonReport { name: suite, tests };
+    let mut json = serde_json::to_string_pretty(&report).unwrap();
+    json.push('\n');
+    json
+}
+
+// Options of the TestCases that decide which snapshots a test case's output is
+// compared against.
+#[derive(Copy, Clone)]
+pub(crate) struct Snapshots {
+    pub json_diagnostics: bool,
+    pub output: bool,
+    pub warnings: bool,
+}
+
+// Paths and contents of the snapshots that the test case's output was compared
+// against. Annotated test cases and ones checking error codes have none.
+fn expected_snapshots(test: &TestReport, snapshots: Snapshots) -> Vec<(PathBuf, String)> {
+    let mut extensions = Vec::new();
+    match test.expected {
+        Expected::CompileFail | Expected::CompileFailBuild => {
+            extensions.push(if snapshots.json_diagnostics {
+                "diagnostics.json"
+            } else {
+                "stderr"
+            });
+        }
+        Expected::Pass | Expected::CompilePass | Expected::RunFail => {
+            // Warnings are only compared against a snapshot if requested, and
+            // so is the output of pass tests.
+            if snapshots.warnings {
+                extensions.push("stderr");
+            }
+            let runs = !matches!(test.expected, Expected::CompilePass);
+            if runs && snapshots.output {
+                extensions.push("stdout");
+            }
+            if runs && (snapshots.output || matches!(test.expected, Expected::RunFail)) {
+                extensions.push("run.stderr");
+            }
+        }
+        Expected::Expand => extensions.push("expanded.rs"),
+        Expected::CompileFailAnnotated | Expected::CompileFailWithCodes => {}
+    }
+    extensions
+        .into_iter()
+        .filter_map(|extension| {
+            let snapshot_path = test.path.with_extension(extension);
+            let expected = fs::read_to_string(&snapshot_path).ok()?;
+            Some((snapshot_path, expected.replace("\r\n", "\n")))
+        })
+        .collect()
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace are not allowed in XML
+            // 1.0, and show up in output containing ANSI escape sequences.
+            '\t' | '\n' | '\r' => escaped.push(ch),
+            ch if ch.is_control() => {}
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[test]
+fn test_xml_escape() {
+    assert_eq!(
+        xml_escape("expected `&str`, found \"<T as Trait>\"\x1B[0m\n"),
+        "expected `&amp;str`, found &quot;&lt;T as Trait&gt;&quot;[0m\n",
+    );
+}
+
+#[test]
+fn test_expected_snapshots() {
+    let test = |path: &str, expected: Expected| TestReport {
+        path: PathBuf::from(path),
+        expected,
+        outcome: Outcome::Failed(Error::Mismatch),
+        stderr: String::new(),
+        duration: Duration::ZERO,
+        cached: false,
+    };
+    let snapshot = |path: &str| (PathBuf::from(path), fs::read_to_string(path).unwrap());
+    let none = Snapshots {
+        json_diagnostics: false,
+        output: false,
+        warnings: false,
+    };
+
+    let expand = test("tests/ui/expand.rs", Expected::Expand);
+    let expanded = snapshot("tests/ui/expand.expanded.rs");
+    assert_eq!(expected_snapshots(&expand, none), [expanded]);
+
+    let run_fail = test("tests/ui/run-fail-panic.rs", Expected::RunFail);
+    let run_stderr = snapshot("tests/ui/run-fail-panic.run.stderr");
+    assert_eq!(expected_snapshots(&run_fail, none), [run_stderr]);
+
+    let json = test("tests/ui/json-diagnostics.rs", Expected::CompileFail);
+    let diagnostics = snapshot("tests/ui/json-diagnostics.diagnostics.json");
+    let json_diagnostics = Snapshots {
+        json_diagnostics: true,
+        ..none
+    };
+    assert_eq!(expected_snapshots(&json, json_diagnostics), [diagnostics]);
+    assert_eq!(expected_snapshots(&json, none), []);
+
+    let pass = test("tests/ui/pass-warning.rs", Expected::Pass);
+    let warnings = Snapshots {
+        warnings: true,
+        ..none
+    };
+    assert_eq!(expected_snapshots(&pass, none), []);
+    let stderr = snapshot("tests/ui/pass-warning.stderr");
+    assert_eq!(expected_snapshots(&pass, warnings), [stderr]);
+
+    let output = Snapshots {
+        output: true,
+        ..none
+    };
+    let print_both = test("tests/ui/print-both.rs", Expected::Pass);
+    let stdout = snapshot("tests/ui/print-both.stdout");
+    let run_stderr = snapshot("tests/ui/print-both.run.stderr");
+    assert_eq!(expected_snapshots(&print_both, none), []);
+    assert_eq!(
+        expected_snapshots(&print_both, output),
+        [stdout, run_stderr],
+    );
+}
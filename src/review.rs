@@ -0,0 +1,98 @@
+use crate::error::{Error, Result};
+use crate::message;
+use crate::orphans::SNAPSHOT_EXTENSIONS;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+
+// Under TRYBUILD=review, the actual output of a mismatch is written next to the
+// snapshot as a pending file, for example tests/ui/x.stderr.new next to
+// tests/ui/x.stderr, to be accepted or rejected later by `review`.
+pub(crate) fn pending_path(snapshot_path: &Path) -> PathBuf {
+    let mut path = OsString::from(snapshot_path);
+    path.push(".new");
+    PathBuf::from(path)
+}
+
+// Pending files anywhere under the given directory.
+pub(crate) fn find_pending(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut pending = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).map_err(|err| Error::Open(dir.clone(), err))? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if let Some(name) = path.to_str() {
+                let is_pending = SNAPSHOT_EXTENSIONS
+                    .iter()
+                    .any(|extension| name.ends_with(&format!(".{}.new", extension)));
+                if is_pending {
+                    pending.push(path);
+                }
+            }
+        }
+    }
+    pending.sort();
+    Ok(pending)
+}
+
+// Shows each pending snapshot against the current one and asks whether to
+// accept it, replacing the snapshot, or reject it, deleting the pending file.
+pub(crate) fn review(pending: &[PathBuf]) -> Result<()> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        message::review_not_interactive(pending.len());
+        return Ok(());
+    }
+
+    let mut lines = stdin.lock().lines();
+    for (i, pending_path) in pending.iter().enumerate() {
+        let snapshot_path = pending_path.with_extension("");
+        let expected = fs::read_to_string(&snapshot_path).unwrap_or_default();
+        let actual = fs::read_to_string(pending_path).map_err(Error::ReadStderr)?;
+        message::review_snapshot(&snapshot_path, i + 1, pending.len(), &expected, &actual);
+
+        let decision = loop {
+            message::review_prompt();
+            let Some(line) = lines.next() else {
+                return Ok(());
+            };
+            match line?.trim() {
+                "a" | "accept" => break Some(true),
+                "r" | "reject" => break Some(false),
+                "s" | "skip" | "" => break None,
+                _ => {}
+            }
+        };
+
+        match decision {
+            Some(true) => {
+                fs::rename(pending_path, &snapshot_path).map_err(Error::WriteStderr)?;
+                message::review_accepted(&snapshot_path);
+            }
+            Some(false) => {
+                fs::remove_file(pending_path).map_err(Error::WriteStderr)?;
+                message::review_rejected(&snapshot_path);
+            }
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_pending_path() {
+    let pending = pending_path(Path::new("tests/ui/x.run.stderr"));
+    assert_eq!(pending, Path::new("tests/ui/x.run.stderr.new"));
+    assert_eq!(
+        pending.with_extension(""),
+        Path::new("tests/ui/x.run.stderr")
+    );
+}
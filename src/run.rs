@@ -1,38 +1,149 @@
+use crate::annotations;
+use crate::cache::Cache;
 use crate::cargo::{self, Metadata, PackageMetadata};
 use crate::dependencies::{self, Dependency, EditionOrInherit};
+use crate::diagnostic::{self, Diagnostic};
+use crate::directives::{self, Directives};
 use crate::directory::Directory;
-use crate::env::Update;
+use crate::env::{self, ReportOutput, Update};
 use crate::error::{Error, Result};
 use crate::expand::{expand_globs, ExpandedTest};
 use crate::flock::Lock;
-use crate::manifest::{Bin, Manifest, Name, Package, Workspace};
+use crate::harness;
+use crate::manifest::{AuxManifest, Bin, Edition, Lib, Manifest, Name, Package, Workspace};
 use crate::message::{self, Fail, Warn};
 use crate::normalize::{self, Context, Variations};
+use crate::orphans;
 use crate::path::CanonicalPath;
+use crate::process;
+use crate::report::{self, Outcome, Report, TestReport};
+use crate::review;
+use crate::rustc::{self, Rustc};
+use crate::rustfix;
+use crate::spans;
+use crate::wildcard;
 use crate::{features, Expected, Runner, Test};
 use serde_derive::Deserialize;
 use std::collections::{BTreeMap as Map, BTreeSet as Set};
-use std::env;
 use std::ffi::{OsStr, OsString};
 use std::fs::{self, File};
+use std::iter;
 use std::mem;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
 use std::str;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub(crate) struct Project {
     pub dir: Directory,
-    source_dir: Directory,
+    pub source_dir: Directory,
     pub target_dir: Directory,
     pub name: String,
-    update: Update,
+    pub update: Update,
+    report: Option<ReportOutput>,
     pub has_pass: bool,
+    has_compile_pass: bool,
     has_compile_fail: bool,
+    pub has_compile_fail_build: bool,
     pub features: Option<Vec<String>>,
     pub workspace: Directory,
     pub path_dependencies: Vec<PathDependency>,
-    manifest: Manifest,
+    pub manifest: Manifest,
+    // Bins of the test cases with `//@ edition`, in one package per edition.
+    editions: Map<Edition, Vec<Bin>>,
     pub keep_going: bool,
+    pub snapshot_output: bool,
+    pub snapshot_warnings: bool,
+    pub deny_warnings: bool,
+    pub json_diagnostics: bool,
+    pub check_spans: bool,
+    pub normalizations: Vec<normalize::Custom>,
+    pub timeout: Option<Duration>,
+    cache: Option<Cache>,
+    aux_crates: Map<String, AuxSource>,
+    run_ignored: bool,
+    rustc: Option<Rustc>,
+}
+
+impl Project {
+    // Package containing the test case's bin. Test cases with `//@ edition`
+    // are in a workspace member per edition.
+    pub fn package(&self, bin: &Name) -> String {
+        self.package_name(self.edition(bin))
+    }
+
+    // Like CARGO_MANIFEST_DIR for the test case's package.
+    pub fn package_dir(&self, bin: &Name) -> Directory {
+        match self.edition(bin) {
+            Some(edition) => path!(self.dir / edition_dir(edition) /),
+            None => self.dir.clone(),
+        }
+    }
+
+    // Every package containing test cases.
+    pub fn packages(&self) -> impl Iterator<Item = String> + '_ {
+        let editions = self.editions.keys();
+        iter::once(self.name.clone())
+            .chain(editions.map(|&edition| self.package_name(Some(edition))))
+    }
+
+    fn edition(&self, bin: &Name) -> Option<Edition> {
+        self.editions
+            .iter()
+            .find(|(_edition, bins)| bins.iter().any(|b| b.name.0 == bin.0))
+            .map(|(&edition, _bins)| edition)
+    }
+
+    fn package_name(&self, edition: Option<Edition>) -> String {
+        match edition {
+            Some(edition) => format!("{}-{}", self.name, edition.as_str()),
+            None => self.name.clone(),
+        }
+    }
+
+    // Whether to build a test case all the way with `cargo build`, rather than
+    // only type-check it with `cargo check`. Pass tests need something to
+    // execute, and compile_fail_build tests may only fail once the compiler
+    // instantiates generic code or the linker runs.
+    fn full_build(&self, expected: Expected) -> bool {
+        self.has_pass || matches!(expected, Expected::CompileFailBuild)
+    }
+
+    // Whether to label each test case with what is expected of it, for a mix
+    // of test cases that should compile and ones that should not.
+    fn show_expected(&self) -> bool {
+        (self.has_pass || self.has_compile_pass) && self.has_compile_fail
+    }
+
+    fn is_cached(&self, t: &ExpandedTest) -> bool {
+        // The cache key does not cover the source of aux crates.
+        t.directives.aux_crates.is_empty()
+            && self
+                .cache
+                .as_ref()
+                .is_some_and(|cache| cache.is_fresh(&t.test))
+    }
+
+    // Building any test case by itself cleans the test packages first, which
+    // can delete the dep-info of the test cases built before it, so the cache
+    // picks it up right after each build.
+    fn record_dep_info(&self, tests: &[(&Name, &Test)]) {
+        if let Some(cache) = &self.cache {
+            cache.record_dep_info(self, tests);
+        }
+    }
+}
+
+// Source file of an aux crate, shared by every test case that declares an aux
+// crate by the same name.
+#[derive(Debug)]
+struct AuxSource {
+    src_path: PathBuf,
+    proc_macro: bool,
 }
 
 #[derive(Debug)]
@@ -41,75 +152,121 @@ pub(crate) struct PathDependency {
     pub normalized_path: Directory,
 }
 
-struct Report {
-    failures: usize,
-    created_wip: usize,
-}
-
 impl Runner {
-    pub(crate) fn run(&mut self) {
+    pub(crate) fn run(&mut self) -> Result<Report> {
+        self.run_project().map(|(report, _project)| report)
+    }
+
+    pub(crate) fn run_and_assert(&mut self) {
+        let (report, project) = self
+            .run_project()
+            .unwrap_or_else(|_err| panic!("tests failed"));
+
+        let failures = report.failures();
+        if failures > 0 && project.name != "trybuild-tests" {
+            panic!("{} of {} tests failed", failures, report.tests.len());
+        }
+        let created_wip = report.created_wip();
+        if created_wip > 0 && project.name != "trybuild-tests" {
+            panic!(
+                "successfully created new stderr files for {} test cases",
+                created_wip,
+            );
+        }
+    }
+
+    fn run_project(&mut self) -> Result<(Report, Project)> {
         let mut tests = expand_globs(&self.tests);
+        let orphans = orphans::find(&self.tests, &tests);
         filter(&mut tests);
+        self.run_tests(tests, orphans)
+    }
+
+    // For the custom test harness, which expands the globs and applies its own
+    // filters beforehand.
+    pub(crate) fn run_selected(
+        &mut self,
+        tests: Vec<ExpandedTest>,
+        orphans: Vec<PathBuf>,
+    ) -> Result<Report> {
+        self.run_tests(tests, orphans)
+            .map(|(report, _project)| report)
+    }
+
+    fn run_tests(
+        &mut self,
+        mut tests: Vec<ExpandedTest>,
+        orphans: Vec<PathBuf>,
+    ) -> Result<(Report, Project)> {
+        self.finished = true;
 
-        let (project, _lock) = (|| {
-            let mut project = self.prepare(&tests)?;
+        let (mut project, _lock) = (|| {
+            let mut project = self.prepare(&mut tests)?;
             let lock = Lock::acquire(path!(project.dir / ".lock"))?;
             self.write(&mut project)?;
+            if env::cache()? {
+                project.cache = Cache::load(&project);
+            }
             Ok((project, lock))
         })()
-        .unwrap_or_else(|err| {
-            message::prepare_fail(err);
-            panic!("tests failed");
-        });
+        .inspect_err(message::prepare_fail)?;
 
         print!("\n\n");
 
-        let len = tests.len();
-        let mut report = Report {
-            failures: 0,
-            created_wip: 0,
-        };
+        for test in orphans::empty_globs(&self.tests) {
+            message::empty_glob(test);
+        }
+        if !orphans.is_empty() {
+            let prune = project.update == Update::Prune;
+            if prune {
+                for path in &orphans {
+                    fs::remove_file(path)
+                        .map_err(|err| Error::Open(path.clone(), err))
+                        .inspect_err(message::prepare_fail)?;
+                }
+            }
+            message::orphaned_snapshots(&orphans, prune);
+        }
+
+        let registered: Map<PathBuf, (Name, Test)> = tests
+            .iter()
+            .map(|t| (t.test.path.clone(), (t.name.clone(), t.test.clone())))
+            .collect();
+        let mut report = Report { tests: Vec::new() };
 
         if tests.is_empty() {
             message::no_tests_enabled();
-        } else if project.keep_going && !project.has_pass {
-            report = match self.run_all(&project, tests) {
-                Ok(failures) => failures,
-                Err(err) => {
-                    message::test_fail(err);
-                    Report {
-                        failures: len,
-                        created_wip: 0,
-                    }
-                }
-            }
+        } else if project.keep_going {
+            report.tests = self
+                .run_all(&project, tests)
+                .inspect_err(message::test_fail)?;
         } else {
             for test in tests {
-                match test.run(&project) {
-                    Ok(Outcome::Passed) => {}
-                    Ok(Outcome::CreatedWip) => report.created_wip += 1,
-                    Err(err) => {
-                        report.failures += 1;
-                        message::test_fail(err);
-                    }
-                }
+                report.tests.push(test.run(&project));
             }
         }
 
         print!("\n\n");
 
-        if report.failures > 0 && project.name != "trybuild-tests" {
-            panic!("{} of {} tests failed", report.failures, len);
+        if let Some(mut cache) = project.cache.take() {
+            cache.update(&project, &report.tests, &registered);
+            cache.save();
         }
-        if report.created_wip > 0 && project.name != "trybuild-tests" {
-            panic!(
-                "successfully created new stderr files for {} test cases",
-                report.created_wip,
-            );
+
+        if let Some(output) = &project.report {
+            let snapshots = report::Snapshots {
+                json_diagnostics: project.json_diagnostics,
+                output: project.snapshot_output,
+                warnings: project.snapshot_warnings,
+            };
+            report::write(output, &report, &project.name, snapshots)
+                .inspect_err(message::prepare_fail)?;
         }
+
+        Ok((report, project))
     }
 
-    fn prepare(&self, tests: &[ExpandedTest]) -> Result<Project> {
+    fn prepare(&self, tests: &mut [ExpandedTest]) -> Result<Project> {
         let Metadata {
             target_directory: target_dir,
             workspace_root: workspace,
@@ -117,11 +274,21 @@ impl Runner {
         } = cargo::metadata()?;
 
         let mut has_pass = false;
+        let mut has_compile_pass = false;
         let mut has_compile_fail = false;
-        for e in tests {
+        let mut has_compile_fail_build = false;
+        for e in tests.iter() {
             match e.test.expected {
-                Expected::Pass => has_pass = true,
-                Expected::CompileFail => has_compile_fail = true,
+                Expected::Pass | Expected::RunFail => has_pass = true,
+                Expected::CompilePass => has_compile_pass = true,
+                Expected::CompileFail
+                | Expected::CompileFailAnnotated
+                | Expected::CompileFailWithCodes => has_compile_fail = true,
+                Expected::CompileFailBuild => {
+                    has_compile_fail = true;
+                    has_compile_fail_build = true;
+                }
+                Expected::Expand => {}
             }
         }
 
@@ -130,6 +297,8 @@ impl Runner {
 
         let mut features = features::find();
 
+        let aux_crates = resolve_aux_crates(tests, &source_dir, &source_manifest);
+
         let path_dependencies = source_manifest
             .dependencies
             .iter()
@@ -152,7 +321,7 @@ impl Runner {
         fs::create_dir_all(&project_dir)?;
 
         let project_name = format!("{}-tests", crate_name);
-        let manifest = self.make_manifest(
+        let (mut manifest, editions) = self.make_manifest(
             &workspace,
             &project_name,
             &source_dir,
@@ -160,6 +329,7 @@ impl Runner {
             tests,
             source_manifest,
         )?;
+        add_aux_crates(&mut manifest, tests, &aux_crates, &project_dir);
 
         if let Some(enabled_features) = &mut features {
             enabled_features.retain(|feature| manifest.features.contains_key(feature));
@@ -171,13 +341,42 @@ impl Runner {
             target_dir,
             name: project_name,
             update: Update::env()?,
+            report: ReportOutput::env()?,
             has_pass,
+            has_compile_pass,
             has_compile_fail,
+            has_compile_fail_build,
             features,
             workspace,
             path_dependencies,
             manifest,
+            editions,
             keep_going: false,
+            snapshot_output: self.snapshot_output,
+            snapshot_warnings: self.snapshot_warnings,
+            deny_warnings: self.deny_warnings,
+            json_diagnostics: self.json_diagnostics,
+            check_spans: self.check_spans,
+            normalizations: self.normalizations.clone(),
+            timeout: match self.timeout {
+                Some(timeout) => Some(timeout),
+                None => env::timeout()?,
+            },
+            cache: None,
+            aux_crates,
+            run_ignored: self
+                .harness
+                .as_ref()
+                .is_some_and(harness::Args::run_ignored),
+            rustc: if tests.iter().any(|t| {
+                t.directives.only_nightly
+                    || t.directives.min_rustc.is_some()
+                    || matches!(t.test.expected, Expected::Expand)
+            }) {
+                rustc::query()
+            } else {
+                None
+            },
         })
     }
 
@@ -191,6 +390,54 @@ impl Runner {
         ";
         fs::write(path!(project.dir / "main.rs"), &main_rs[..])?;
 
+        // Stand-ins for the fixed sources of `//@ run-rustfix` test cases, which
+        // are only known once the test case has been built.
+        let fixed_dir = path!(project.dir / "fixed");
+        let edition_bins = project.editions.values().flatten();
+        for bin in project.manifest.bins.iter().chain(edition_bins) {
+            if bin.path.starts_with("fixed") {
+                fs::create_dir_all(&fixed_dir)?;
+                fs::write(path!(project.dir / bin.path), &main_rs[..])?;
+            }
+        }
+
+        for (&edition, bins) in &project.editions {
+            let edition_dir = path!(project.dir / edition_dir(edition));
+            fs::create_dir_all(&edition_dir)?;
+            let edition_manifest = edition_manifest(project, edition, bins)?;
+            let edition_manifest_toml = toml::to_string(&edition_manifest)?;
+            fs::write(path!(edition_dir / "Cargo.toml"), edition_manifest_toml)?;
+        }
+
+        for (name, aux) in &project.aux_crates {
+            let aux_dir = path!(project.dir / "aux" / name);
+            fs::create_dir_all(&aux_dir)?;
+            let aux_manifest = AuxManifest {
+                package: Package {
+                    name: name.clone(),
+                    version: "0.0.0".to_owned(),
+                    edition: project.manifest.package.edition,
+                    resolver: None,
+                    publish: false,
+                },
+                lib: Lib {
+                    path: aux.src_path.clone(),
+                    proc_macro: aux.proc_macro,
+                },
+                // Everything available to the test cases, other than the aux
+                // crates themselves.
+                dependencies: project
+                    .manifest
+                    .dependencies
+                    .iter()
+                    .filter(|(_name, dep)| !dep.optional)
+                    .map(|(name, dep)| (name.clone(), dep.clone()))
+                    .collect(),
+            };
+            let aux_manifest_toml = toml::to_string(&aux_manifest)?;
+            fs::write(path!(aux_dir / "Cargo.toml"), aux_manifest_toml)?;
+        }
+
         cargo::build_dependencies(project)?;
 
         Ok(())
@@ -204,7 +451,7 @@ impl Runner {
         packages: &[PackageMetadata],
         tests: &[ExpandedTest],
         source_manifest: dependencies::Manifest,
-    ) -> Result<Manifest> {
+    ) -> Result<(Manifest, Map<Edition, Vec<Bin>>)> {
         let crate_name = source_manifest.package.name;
         let workspace_manifest = dependencies::get_workspace_manifest(workspace);
 
@@ -279,7 +526,34 @@ impl Runner {
             }
         }
 
-        let mut manifest = Manifest {
+        let mut bins = vec![Bin {
+            name: Name(project_name.to_owned()),
+            path: Path::new("main.rs").to_owned(),
+        }];
+        let mut editions = Map::new();
+
+        for expanded in tests {
+            if expanded.error.is_none() {
+                // Cargo's `edition` key on [[bin]] is deprecated, so test cases
+                // with `//@ edition` go in a workspace member per edition.
+                let bins = match expanded.directives.edition {
+                    Some(edition) => editions.entry(edition).or_insert_with(Vec::new),
+                    None => &mut bins,
+                };
+                bins.push(Bin {
+                    name: expanded.name.clone(),
+                    path: expanded.test.src_path(source_dir),
+                });
+                if expanded.directives.build_fixed {
+                    bins.push(Bin {
+                        name: expanded.name.fixed(),
+                        path: Path::new("fixed").join(format!("{}.rs", expanded.name.0)),
+                    });
+                }
+            }
+        }
+
+        let manifest = Manifest {
             cargo_features: source_manifest.cargo_features,
             package: Package {
                 name: project_name.to_owned(),
@@ -291,8 +565,12 @@ impl Runner {
             features,
             dependencies,
             target: targets,
-            bins: Vec::new(),
+            bins,
             workspace: Some(Workspace {
+                members: editions
+                    .keys()
+                    .map(|&edition| edition_dir(edition))
+                    .collect(),
                 dependencies: workspace_manifest.workspace.dependencies,
             }),
             // Within a workspace, only the [patch] and [replace] sections in
@@ -301,203 +579,699 @@ impl Runner {
             replace: workspace_manifest.replace,
         };
 
-        manifest.bins.push(Bin {
-            name: Name(project_name.to_owned()),
-            path: Path::new("main.rs").to_owned(),
-        });
-
-        for expanded in tests {
-            if expanded.error.is_none() {
-                manifest.bins.push(Bin {
-                    name: expanded.name.clone(),
-                    path: source_dir.join(&expanded.test.path),
-                });
-            }
-        }
-
-        Ok(manifest)
+        Ok((manifest, editions))
     }
 
-    fn run_all(&self, project: &Project, tests: Vec<ExpandedTest>) -> Result<Report> {
-        let mut report = Report {
-            failures: 0,
-            created_wip: 0,
-        };
-
+    fn run_all(&self, project: &Project, tests: Vec<ExpandedTest>) -> Result<Vec<TestReport>> {
         let mut path_map = Map::new();
         for t in &tests {
-            let src_path = CanonicalPath::new(&project.source_dir.join(&t.test.path));
+            let src_path = CanonicalPath::new(&t.test.src_path(&project.source_dir));
             path_map.insert(src_path, (&t.name, &t.test));
         }
 
-        let output = cargo::build_all_tests(project)?;
-        let parsed = parse_cargo_json(project, &output.stdout, &path_map);
-        let fallback = Stderr::default();
+        let cached: Vec<bool> = tests
+            .iter()
+            .map(|t| t.error.is_none() && project.is_cached(t))
+            .collect();
+        // One batch of test cases to type-check and one to build, unless there
+        // are pass tests which get everything built.
+        let (build, check): (Vec<&ExpandedTest>, Vec<&ExpandedTest>) = tests
+            .iter()
+            .zip(&cached)
+            .filter(|(t, cached)| {
+                t.error.is_none()
+                    && !t.needs_own_build()
+                    && t.ignore_reason(project).is_none()
+                    && !**cached
+            })
+            .map(|(t, _cached)| t)
+            .partition(|t| project.full_build(t.test.expected));
+
+        let mut parsed = ParsedOutputs::default();
+        let mut timed_out = false;
+        for (batch, full_build) in [(check, false), (build, true)] {
+            if batch.is_empty() {
+                continue;
+            }
+            let names: Vec<&Name> = batch.iter().map(|t| &t.name).collect();
+            let Some(output) = cargo::build_all_tests(project, &names, full_build)? else {
+                message::batch_timed_out();
+                timed_out = true;
+                break;
+            };
+            let built: Vec<(&Name, &Test)> = batch.iter().map(|t| (&t.name, &t.test)).collect();
+            project.record_dep_info(&built);
+            let batch_parsed = parse_cargo_json(project, &output.stdout, &path_map);
+            parsed.stderrs.extend(batch_parsed.stderrs);
+        }
+        if timed_out {
+            // Build them one at a time to find out which test case hangs.
+            return Ok(tests.into_iter().map(|t| t.run(project)).collect());
+        }
+
+        let mut commands = Vec::new();
+        for (src_path, this_test) in &mut parsed.stderrs {
+            let Some((name, test)) = path_map.get(src_path) else {
+                continue;
+            };
+            if this_test.success && matches!(test.expected, Expected::Pass | Expected::RunFail) {
+                let cmd = test.run_command(project, name, this_test);
+                commands.push((cmd, &*test.path, &mut this_test.output));
+            }
+        }
+        execute_all(commands, project.timeout);
+
+        let mut reports = Vec::new();
+        for (mut t, cached) in tests.into_iter().zip(cached) {
+            if t.error.is_none() && (t.needs_own_build() || t.ignore_reason(project).is_some()) {
+                reports.push(t.run(project));
+                continue;
+            }
 
-        for mut t in tests {
-            let show_expected = false;
+            let start = Instant::now();
+            let show_expected = t.error.is_none() && project.show_expected();
             message::begin_test(&t.test, show_expected);
 
+            if cached {
+                reports.push(t.test.cached_report(start));
+                continue;
+            }
+
             if t.error.is_none() {
                 t.error = check_exists(&t.test.path).err();
             }
 
-            if t.error.is_none() {
-                let src_path = CanonicalPath::new(&project.source_dir.join(&t.test.path));
-                let this_test = parsed.stderrs.get(&src_path).unwrap_or(&fallback);
-                match t.test.check(project, &t.name, this_test, "") {
-                    Ok(Outcome::Passed) => {}
-                    Ok(Outcome::CreatedWip) => report.created_wip += 1,
-                    Err(error) => t.error = Some(error),
+            let report = match t.error {
+                Some(err) => t.test.report(Err(err), "", start),
+                None => {
+                    let src_path = CanonicalPath::new(&t.test.src_path(&project.source_dir));
+                    let mut this_test = parsed.stderrs.remove(&src_path).unwrap_or_default();
+                    let result = t.check(project, &mut this_test, "");
+                    t.test.report(result, this_test.stderr.preferred(), start)
                 }
-            }
+            };
 
-            if let Some(err) = t.error {
-                report.failures += 1;
-                message::test_fail(err);
-            }
+            reports.push(report);
         }
 
-        Ok(report)
+        Ok(reports)
     }
 }
 
-enum Outcome {
-    Passed,
-    CreatedWip,
-}
-
 impl Test {
-    fn run(&self, project: &Project, name: &Name) -> Result<Outcome> {
-        let show_expected = project.has_pass && project.has_compile_fail;
-        message::begin_test(self, show_expected);
+    // A test case is either a single source file, or a directory whose main.rs
+    // is the crate root and whose other files are modules of it.
+    pub(crate) fn src_path(&self, source_dir: &Directory) -> PathBuf {
+        let path = source_dir.join(&self.path);
+        if path.is_dir() {
+            path.join("main.rs")
+        } else {
+            path
+        }
+    }
+
+    fn build(
+        &self,
+        project: &Project,
+        name: &Name,
+        directives: &Directives,
+    ) -> Result<(Stderr, String)> {
         check_exists(&self.path)?;
 
         let mut path_map = Map::new();
-        let src_path = CanonicalPath::new(&project.source_dir.join(&self.path));
+        let src_path = CanonicalPath::new(&self.src_path(&project.source_dir));
         path_map.insert(src_path.clone(), (name, self));
 
-        let output = cargo::build_test(project, name)?;
-        let parsed = parse_cargo_json(project, &output.stdout, &path_map);
-        let fallback = Stderr::default();
-        let this_test = parsed.stderrs.get(&src_path).unwrap_or(&fallback);
-        self.check(project, name, this_test, &parsed.stdout)
+        if let Expected::Expand = self.expected {
+            return self.build_expansion(project, name, directives);
+        }
+
+        let full_build = project.full_build(self.expected);
+        let Some(output) = cargo::build_test(project, name, directives, full_build)? else {
+            return Err(Error::Timeout(
+                self.path.clone(),
+                project.timeout.unwrap_or_default(),
+            ));
+        };
+        project.record_dep_info(&[(name, self)]);
+        let mut parsed = parse_cargo_json(project, &output.stdout, &path_map);
+        let this_test = parsed.stderrs.remove(&src_path).unwrap_or_default();
+        Ok((this_test, parsed.stdout))
+    }
+
+    fn build_expansion(
+        &self,
+        project: &Project,
+        name: &Name,
+        directives: &Directives,
+    ) -> Result<(Stderr, String)> {
+        let bootstrap = !project.rustc.as_ref().is_some_and(|rustc| rustc.nightly);
+        let Some(output) = cargo::expand_test(project, name, directives, bootstrap)? else {
+            return Err(Error::Timeout(
+                self.path.clone(),
+                project.timeout.unwrap_or_default(),
+            ));
+        };
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let this_test = Stderr {
+            success: output.status.success(),
+            stderr: normalize::diagnostics(&stderr, context(project, name, self)),
+            output: Some(Ok(output)),
+            ..Stderr::default()
+        };
+        Ok((this_test, String::new()))
+    }
+
+    fn report(self, result: Result<Outcome>, stderr: &str, start: Instant) -> TestReport {
+        let outcome = match result {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                message::test_fail(&err);
+                Outcome::Failed(err)
+            }
+        };
+        TestReport {
+            path: self.path,
+            expected: self.expected,
+            outcome,
+            stderr: stderr.to_owned(),
+            duration: start.elapsed(),
+            cached: false,
+        }
+    }
+
+    fn cached_report(self, start: Instant) -> TestReport {
+        message::cached();
+        TestReport {
+            cached: true,
+            ..self.report(Ok(Outcome::Passed), "", start)
+        }
     }
 
     fn check(
         &self,
         project: &Project,
         name: &Name,
-        result: &Stderr,
+        result: &mut Stderr,
         build_stdout: &str,
     ) -> Result<Outcome> {
         let check = match self.expected {
             Expected::Pass => Test::check_pass,
-            Expected::CompileFail => Test::check_compile_fail,
+            Expected::CompilePass => Test::check_compile_pass,
+            Expected::CompileFail | Expected::CompileFailBuild => Test::check_compile_fail,
+            Expected::CompileFailAnnotated => Test::check_compile_fail_annotated,
+            Expected::CompileFailWithCodes => Test::check_compile_fail_with_codes,
+            Expected::RunFail => Test::check_run_fail,
+            Expected::Expand => Test::check_expand,
         };
 
-        check(
-            self,
-            project,
-            name,
-            result.success,
-            build_stdout,
-            &result.stderr,
-        )
+        check(self, project, name, result, build_stdout)
+    }
+
+    // Command to run the test case's executable after it compiled successfully.
+    fn run_command(&self, project: &Project, name: &Name, result: &Stderr) -> Command {
+        // Keep the output stable for comparison against a snapshot.
+        let snapshot = match self.expected {
+            Expected::RunFail => true,
+            _ => project.snapshot_output,
+        };
+        cargo::run_test(project, name, result.executable.as_deref(), snapshot)
+    }
+
+    // Runs the test case's executable, unless it already ran together with the
+    // rest of a batch.
+    fn execute(
+        &self,
+        project: &Project,
+        name: &Name,
+        result: &Stderr,
+        output: Option<Result<Output>>,
+    ) -> Result<Output> {
+        match output {
+            Some(output) => output,
+            None => {
+                let mut cmd = self.run_command(project, name, result);
+                execute(&mut cmd, &self.path, project.timeout)
+            }
+        }
     }
 
     fn check_pass(
         &self,
         project: &Project,
         name: &Name,
-        success: bool,
+        result: &mut Stderr,
         build_stdout: &str,
-        variations: &Variations,
     ) -> Result<Outcome> {
+        let output = result.output.take();
+        let preferred = result.stderr.preferred();
+        if !result.success {
+            message::failed_to_build(preferred);
+            return Err(Error::CargoFail);
+        }
+
+        let warnings_outcome = self.check_warnings(project, &result.stderr)?;
+        let warnings = if project.snapshot_warnings {
+            ""
+        } else {
+            preferred
+        };
+
+        let mut output = self.execute(project, name, result, output)?;
+        let outcome = if project.snapshot_output && output.status.success() {
+            let snapshot_stdout = true;
+            self.check_output_snapshots(project, name, warnings, &output, snapshot_stdout)
+        } else {
+            output.stdout.splice(..0, build_stdout.bytes());
+            message::output(warnings, &output);
+            if output.status.success() {
+                Ok(Outcome::Passed)
+            } else {
+                Err(Error::RunFailed)
+            }
+        };
+
+        combine(warnings_outcome, outcome)
+    }
+
+    // Unlike check_pass, nothing gets run. The test case only needs to compile.
+    fn check_compile_pass(
+        &self,
+        project: &Project,
+        _name: &Name,
+        result: &mut Stderr,
+        _build_stdout: &str,
+    ) -> Result<Outcome> {
+        let preferred = result.stderr.preferred();
+        if !result.success {
+            message::failed_to_build(preferred);
+            return Err(Error::CargoFail);
+        }
+
+        let warnings_outcome = self.check_warnings(project, &result.stderr)?;
+        message::compiled(if project.snapshot_warnings {
+            ""
+        } else {
+            preferred
+        });
+        combine(warnings_outcome, Ok(Outcome::Passed))
+    }
+
+    // For test cases that are expected to compile, checks the compiler's
+    // warnings according to `deny_warnings` and `snapshot_warnings`.
+    fn check_warnings(
+        &self,
+        project: &Project,
+        variations: &Variations,
+    ) -> Result<Option<Outcome>> {
         let preferred = variations.preferred();
-        if !success {
+
+        if project.deny_warnings && !preferred.is_empty() {
+            message::unexpected_warnings(preferred);
+            return Err(Error::Warnings);
+        }
+
+        if !project.snapshot_warnings {
+            return Ok(None);
+        }
+
+        let stderr_path = self.path.with_extension("stderr");
+        if preferred.is_empty() && !stderr_path.exists() {
+            return Ok(None);
+        }
+
+        let is_match =
+            |expected: &str| variations.any(|stderr| wildcard::matches(expected, stderr));
+        check_snapshot(project, &stderr_path, preferred, is_match)
+    }
+
+    fn check_output_snapshots(
+        &self,
+        project: &Project,
+        name: &Name,
+        warnings: &str,
+        output: &Output,
+        snapshot_stdout: bool,
+    ) -> Result<Outcome> {
+        let context = context(project, name, self);
+        let stdout = normalize::runtime_output(&output.stdout, context);
+        let stderr = normalize::runtime_output(&output.stderr, context);
+
+        let mut result = Ok(Outcome::Passed);
+        let mut printed = false;
+        for (extension, actual) in [("stdout", &stdout), ("run.stderr", &stderr)] {
+            if extension == "stdout" && !snapshot_stdout {
+                continue;
+            }
+            let snapshot_path = self.path.with_extension(extension);
+            if actual.is_empty() && !snapshot_path.exists() {
+                continue;
+            }
+            let is_match = |expected: &str| expected == actual;
+            match check_snapshot(project, &snapshot_path, actual, is_match) {
+                Ok(None) => continue,
+                Ok(Some(Outcome::CreatedWip)) => {
+                    if result.is_ok() {
+                        result = Ok(Outcome::CreatedWip);
+                    }
+                }
+                Ok(Some(_)) => {}
+                Err(err) => result = Err(err),
+            }
+            printed = true;
+        }
+
+        if !printed {
+            message::ok();
+            if !warnings.is_empty() {
+                println!();
+            }
+        }
+        message::warnings(warnings);
+        result
+    }
+
+    fn check_expand(
+        &self,
+        project: &Project,
+        name: &Name,
+        result: &mut Stderr,
+        _build_stdout: &str,
+    ) -> Result<Outcome> {
+        let output = result.output.take();
+        if !result.success {
+            message::failed_to_build(result.stderr.preferred());
+            return Err(Error::CargoFail);
+        }
+
+        let output = output.unwrap_or(Err(Error::CargoFail))?;
+        let expanded = normalize::expanded(&output.stdout, context(project, name, self));
+        let snapshot_path = self.path.with_extension("expanded.rs");
+        let is_match = |expected: &str| expected == expanded;
+        match check_snapshot(project, &snapshot_path, &expanded, is_match)? {
+            Some(outcome) => Ok(outcome),
+            None => {
+                message::ok();
+                Ok(Outcome::Passed)
+            }
+        }
+    }
+
+    fn check_run_fail(
+        &self,
+        project: &Project,
+        name: &Name,
+        result: &mut Stderr,
+        build_stdout: &str,
+    ) -> Result<Outcome> {
+        let output = result.output.take();
+        let preferred = result.stderr.preferred();
+        if !result.success {
             message::failed_to_build(preferred);
             return Err(Error::CargoFail);
         }
 
-        let mut output = cargo::run_test(project, name)?;
-        output.stdout.splice(..0, build_stdout.bytes());
-        message::output(preferred, &output);
-        if output.status.success() {
-            Ok(Outcome::Passed)
+        let warnings_outcome = self.check_warnings(project, &result.stderr)?;
+        let warnings = if project.snapshot_warnings {
+            ""
         } else {
-            Err(Error::RunFailed)
+            preferred
+        };
+
+        let mut output = self.execute(project, name, result, output)?;
+        let expected_code = self.exit_code;
+
+        if output.status.success() {
+            output.stdout.splice(..0, build_stdout.bytes());
+            message::should_not_have_run_successfully(warnings, &output);
+            return Err(Error::RunSucceeded);
         }
+
+        if let Some(expected_code) = expected_code {
+            let actual_code = output.status.code();
+            if actual_code != Some(expected_code) {
+                output.stdout.splice(..0, build_stdout.bytes());
+                message::wrong_exit_code(expected_code, actual_code, warnings, &output);
+                return Err(Error::ExitCode(expected_code, actual_code));
+            }
+        }
+
+        let snapshot_stdout = project.snapshot_output;
+        let outcome =
+            self.check_output_snapshots(project, name, warnings, &output, snapshot_stdout);
+        combine(warnings_outcome, outcome)
     }
 
     fn check_compile_fail(
         &self,
         project: &Project,
-        _name: &Name,
-        success: bool,
+        name: &Name,
+        result: &mut Stderr,
         build_stdout: &str,
-        variations: &Variations,
     ) -> Result<Outcome> {
+        let variations = &result.stderr;
         let preferred = variations.preferred();
 
-        if success {
+        if result.success {
             message::should_not_have_compiled();
             message::fail_output(Fail, build_stdout);
             message::warnings(preferred);
             return Err(Error::ShouldNotHaveCompiled);
         }
 
-        let stderr_path = self.path.with_extension("stderr");
+        let snapshot_path = if project.json_diagnostics {
+            self.path.with_extension("diagnostics.json")
+        } else {
+            self.path.with_extension("stderr")
+        };
+        let created = !snapshot_path.exists();
+        let outcome = if project.json_diagnostics {
+            let json = diagnostic::snapshot(&result.diagnostics, context(project, name, self));
+            check_snapshot(project, &snapshot_path, &json, |expected| expected == json)?
+        } else {
+            let is_match =
+                |expected: &str| variations.any(|stderr| wildcard::matches(expected, stderr));
+            check_snapshot(project, &snapshot_path, preferred, is_match)?
+        };
+        match outcome {
+            None => {
+                message::ok();
+                Ok(Outcome::Passed)
+            }
+            Some(outcome) => {
+                if created {
+                    message::fail_output(Warn, build_stdout);
+                }
+                Ok(outcome)
+            }
+        }
+    }
+
+    fn check_compile_fail_annotated(
+        &self,
+        project: &Project,
+        _name: &Name,
+        result: &mut Stderr,
+        build_stdout: &str,
+    ) -> Result<Outcome> {
+        let preferred = result.stderr.preferred();
+
+        if result.success {
+            message::should_not_have_compiled();
+            message::fail_output(Fail, build_stdout);
+            message::warnings(preferred);
+            return Err(Error::ShouldNotHaveCompiled);
+        }
+
+        let files = annotations::source_files(&project.source_dir, &self.path);
+        let mut expected = Vec::new();
+        for file in &files {
+            let path = project.source_dir.join(file);
+            let source = fs::read_to_string(&path).map_err(|err| Error::Open(path, err))?;
+            expected.extend(annotations::parse(file, &source));
+        }
+
+        match annotations::check(&project.source_dir, &files, expected, &result.diagnostics) {
+            Ok(()) => {
+                message::ok();
+                Ok(Outcome::Passed)
+            }
+            Err(mismatch) => {
+                message::annotations_mismatch(&mismatch, preferred);
+                Err(Error::Mismatch)
+            }
+        }
+    }
 
-        if !stderr_path.exists() {
-            let outcome = match project.update {
-                Update::Wip => {
-                    let wip_dir = Path::new("wip");
-                    fs::create_dir_all(wip_dir)?;
-                    let gitignore_path = wip_dir.join(".gitignore");
-                    fs::write(gitignore_path, "*\n")?;
-                    let stderr_name = stderr_path
-                        .file_name()
-                        .unwrap_or_else(|| OsStr::new("test.stderr"));
-                    let wip_path = wip_dir.join(stderr_name);
-                    message::write_stderr_wip(&wip_path, &stderr_path, preferred);
-                    fs::write(wip_path, preferred).map_err(Error::WriteStderr)?;
-                    Outcome::CreatedWip
+    fn check_compile_fail_with_codes(
+        &self,
+        _project: &Project,
+        _name: &Name,
+        result: &mut Stderr,
+        build_stdout: &str,
+    ) -> Result<Outcome> {
+        let preferred = result.stderr.preferred();
+
+        if result.success {
+            message::should_not_have_compiled();
+            message::fail_output(Fail, build_stdout);
+            message::warnings(preferred);
+            return Err(Error::ShouldNotHaveCompiled);
+        }
+
+        let expected: Set<&str> = self.codes.iter().map(String::as_str).collect();
+        let mut actual = Set::new();
+        let mut uncoded = 0;
+        for diag in &result.diagnostics {
+            if diag.level != "error" || diagnostic::is_summary(diag) {
+                continue;
+            }
+            match &diag.code {
+                Some(code) => {
+                    actual.insert(code.code.as_str());
                 }
-                Update::Overwrite => {
-                    message::overwrite_stderr(&stderr_path, preferred);
-                    fs::write(stderr_path, preferred).map_err(Error::WriteStderr)?;
-                    Outcome::Passed
+                None => {
+                    actual.insert(UNCODED);
+                    uncoded += 1;
                 }
-            };
-            message::fail_output(Warn, build_stdout);
-            return Ok(outcome);
+            }
         }
 
-        let expected = fs::read_to_string(&stderr_path)
-            .map_err(Error::ReadStderr)?
-            .replace("\r\n", "\n");
-
-        if variations.any(|stderr| expected == stderr) {
+        if expected == actual {
             message::ok();
-            return Ok(Outcome::Passed);
+            Ok(Outcome::Passed)
+        } else {
+            let expected = Vec::from_iter(expected);
+            let actual = Vec::from_iter(actual);
+            message::codes_mismatch(&expected, &actual, uncoded, preferred);
+            Err(Error::Mismatch)
         }
+    }
+}
 
-        match project.update {
-            Update::Wip => {
-                message::mismatch(&expected, preferred);
-                Err(Error::Mismatch)
+// Stands for errors without a code in compile_fail_with_codes.
+pub(crate) const UNCODED: &str = "uncoded";
+
+fn execute(cmd: &mut Command, path: &Path, timeout: Option<Duration>) -> Result<Output> {
+    match process::output(cmd, timeout) {
+        Ok(Some(output)) => Ok(output),
+        Ok(None) => Err(Error::Timeout(path.to_owned(), timeout.unwrap_or_default())),
+        Err(err) => Err(Error::Io(err)),
+    }
+}
+
+// Runs the executables of a batch of test cases, several at a time.
+fn execute_all(
+    commands: Vec<(Command, &Path, &mut Option<Result<Output>>)>,
+    timeout: Option<Duration>,
+) {
+    let jobs = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let queue = Mutex::new(commands.into_iter());
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                let Some((mut cmd, path, output)) = next else {
+                    break;
+                };
+                *output = Some(execute(&mut cmd, path, timeout));
+            });
+        }
+    });
+}
+
+// Combines the outcome of checking a snapshot, if any, with the outcome of the
+// rest of the test case.
+fn combine(snapshot_outcome: Option<Outcome>, outcome: Result<Outcome>) -> Result<Outcome> {
+    match (snapshot_outcome, outcome) {
+        (Some(Outcome::CreatedWip), Ok(_)) => Ok(Outcome::CreatedWip),
+        (_, outcome) => outcome,
+    }
+}
+
+// Compares actual output against a snapshot file. Returns None if the snapshot
+// matches, or the outcome of writing a new snapshot according to $TRYBUILD.
+fn check_snapshot(
+    project: &Project,
+    snapshot_path: &Path,
+    preferred: &str,
+    is_match: impl Fn(&str) -> bool,
+) -> Result<Option<Outcome>> {
+    if !snapshot_path.exists() {
+        let outcome = match project.update {
+            Update::Wip | Update::Prune => {
+                let wip_dir = Path::new("wip");
+                fs::create_dir_all(wip_dir)?;
+                let gitignore_path = wip_dir.join(".gitignore");
+                fs::write(gitignore_path, "*\n")?;
+                let snapshot_name = snapshot_path
+                    .file_name()
+                    .unwrap_or_else(|| OsStr::new("test.stderr"));
+                let wip_path = wip_dir.join(snapshot_name);
+                message::write_stderr_wip(&wip_path, snapshot_path, preferred);
+                fs::write(wip_path, preferred).map_err(Error::WriteStderr)?;
+                Outcome::CreatedWip
+            }
+            Update::Review => {
+                let pending_path = review::pending_path(snapshot_path);
+                message::write_stderr_wip(&pending_path, snapshot_path, preferred);
+                fs::write(pending_path, preferred).map_err(Error::WriteStderr)?;
+                Outcome::CreatedWip
             }
             Update::Overwrite => {
-                message::overwrite_stderr(&stderr_path, preferred);
-                fs::write(stderr_path, preferred).map_err(Error::WriteStderr)?;
-                Ok(Outcome::Passed)
+                message::overwrite_stderr(snapshot_path, preferred);
+                fs::write(snapshot_path, preferred).map_err(Error::WriteStderr)?;
+                Outcome::Passed
             }
+        };
+        return Ok(Some(outcome));
+    }
+
+    let expected = fs::read_to_string(snapshot_path)
+        .map_err(Error::ReadStderr)?
+        .replace("\r\n", "\n");
+
+    let pending_path = review::pending_path(snapshot_path);
+    if is_match(&expected) {
+        // Left over from an earlier mismatch that has since been fixed.
+        let _ = fs::remove_file(pending_path);
+        return Ok(None);
+    }
+
+    // Keep whichever wildcards still match, if the snapshot is one that
+    // supports them.
+    let mut blessed = wildcard::overwrite(&expected, preferred);
+    if !is_match(&blessed) {
+        blessed = preferred.to_owned();
+    }
+
+    match project.update {
+        Update::Wip | Update::Prune => {
+            message::mismatch(&expected, preferred, None);
+            Err(Error::Mismatch)
+        }
+        Update::Review => {
+            message::mismatch(&expected, preferred, Some(&pending_path));
+            fs::write(pending_path, blessed).map_err(Error::WriteStderr)?;
+            Err(Error::Mismatch)
+        }
+        Update::Overwrite => {
+            message::overwrite_stderr(snapshot_path, &blessed);
+            fs::write(snapshot_path, blessed).map_err(Error::WriteStderr)?;
+            let _ = fs::remove_file(pending_path);
+            Ok(Some(Outcome::Passed))
         }
     }
 }
 
 fn check_exists(path: &Path) -> Result<()> {
+    let main_path;
+    let path = if path.is_dir() {
+        main_path = path.join("main.rs");
+        &main_path
+    } else {
+        path
+    };
     if path.exists() {
         return Ok(());
     }
@@ -508,14 +1282,264 @@ fn check_exists(path: &Path) -> Result<()> {
 }
 
 impl ExpandedTest {
-    fn run(self, project: &Project) -> Result<Outcome> {
-        match self.error {
-            None => self.test.run(project, &self.name),
-            Some(error) => {
-                let show_expected = false;
-                message::begin_test(&self.test, show_expected);
-                Err(error)
+    // Test cases with directives are built by themselves. Flags, environment
+    // variables and features would otherwise apply to the whole batch, and so
+    // would aux crates. Test cases with an edition of their own are in a
+    // different package than the batch.
+    fn needs_own_build(&self) -> bool {
+        self.directives.needs_own_build() || matches!(self.test.expected, Expected::Expand)
+    }
+
+    // Like Test::check, but first checks the spans of the errors if asked to,
+    // and the source with the compiler's suggestions applied if the test case
+    // has `//@ run-rustfix`.
+    fn check(&self, project: &Project, result: &mut Stderr, build_stdout: &str) -> Result<Outcome> {
+        if project.check_spans || self.directives.check_spans {
+            let input = project.source_dir.join(&self.test.path);
+            let context = context(project, &self.name, &self.test);
+            let bad_spans = spans::check(&input, &result.diagnostics, context);
+            if !bad_spans.is_empty() {
+                message::bad_spans(&bad_spans, result.stderr.preferred());
+                return Err(Error::BadSpans);
+            }
+        }
+
+        let fixed_outcome = if self.directives.run_rustfix {
+            self.check_rustfix(project, result)?
+        } else {
+            None
+        };
+        let outcome = self.test.check(project, &self.name, result, build_stdout)?;
+        Ok(match fixed_outcome {
+            Some(Outcome::CreatedWip) => Outcome::CreatedWip,
+            _ => outcome,
+        })
+    }
+
+    fn check_rustfix(&self, project: &Project, result: &Stderr) -> Result<Option<Outcome>> {
+        let src_path = self.test.src_path(&project.source_dir);
+        let source =
+            fs::read_to_string(&src_path).map_err(|err| Error::Open(src_path.clone(), err))?;
+        let fixed = rustfix::apply(&source, &src_path, &result.diagnostics);
+
+        let fixed_path = self.test.path.with_extension("fixed");
+        let is_match = |expected: &str| expected == fixed;
+        let outcome = check_snapshot(project, &fixed_path, &fixed, is_match)?;
+        if !self.directives.build_fixed {
+            return Ok(outcome);
+        }
+
+        let fixed_src_path = path!(project.dir / "fixed" / format!("{}.rs", self.name.0));
+        fs::write(&fixed_src_path, &fixed)?;
+        let full_build = project.full_build(self.test.expected);
+        let Some(output) =
+            cargo::build_fixed_test(project, &self.name, &self.directives, full_build)?
+        else {
+            return Err(Error::Timeout(
+                fixed_path,
+                project.timeout.unwrap_or_default(),
+            ));
+        };
+        let mut path_map = Map::new();
+        let fixed_src_path = CanonicalPath::new(&fixed_src_path);
+        path_map.insert(fixed_src_path.clone(), (&self.name, &self.test));
+        let mut parsed = parse_cargo_json(project, &output.stdout, &path_map);
+        let fixed_result = parsed.stderrs.remove(&fixed_src_path).unwrap_or_default();
+        if !output.status.success() || !fixed_result.success {
+            message::fixed_failed_to_build(&fixed_path, fixed_result.stderr.preferred());
+            return Err(Error::FixedFailed);
+        }
+
+        Ok(outcome)
+    }
+
+    // Why the test case is not being run, if so.
+    fn ignore_reason(&self, project: &Project) -> Option<String> {
+        if let Some(reason) = &self.directives.ignore {
+            if !project.run_ignored {
+                return Some(reason.clone());
+            }
+        }
+        // If the compiler's version cannot be determined, run the test case
+        // and let it fail if it needs a newer one.
+        if let (Some(min_rustc), Some(rustc)) = (self.directives.min_rustc, &project.rustc) {
+            if rustc.version < min_rustc {
+                return Some(format!("requires rustc {} or newer", min_rustc));
+            }
+        }
+        if self.directives.only_nightly
+            && !project.rustc.as_ref().is_some_and(|rustc| rustc.nightly)
+        {
+            return Some("requires a nightly compiler".to_owned());
+        }
+        None
+    }
+
+    fn collect_aux_crates(
+        &self,
+        source_dir: &Directory,
+        source_manifest: &dependencies::Manifest,
+        aux_crates: &mut Map<String, AuxSource>,
+    ) -> Result<()> {
+        let src_path = self.test.src_path(source_dir);
+        let directive_error = |msg| Error::Directive(self.test.path.clone(), msg);
+
+        let test_dir = src_path.parent().unwrap_or(Path::new(""));
+        for aux in &self.directives.aux_crates {
+            if aux.name == source_manifest.package.name
+                || source_manifest.dependencies.contains_key(&aux.name)
+                || source_manifest.dev_dependencies.contains_key(&aux.name)
+            {
+                let msg = format!("aux crate `{}` conflicts with a dependency", aux.name);
+                return Err(directive_error(msg));
             }
+
+            let aux_path = test_dir.join(&aux.path);
+            let aux_source =
+                fs::read_to_string(&aux_path).map_err(|err| Error::Open(aux_path.clone(), err))?;
+            let aux_directives = directives::parse(&aux_source)
+                .map_err(|msg| Error::Directive(aux_path.clone(), msg))?;
+            if !aux_directives.aux_crates.is_empty() {
+                let msg = "aux crates cannot declare aux crates of their own".to_owned();
+                return Err(Error::Directive(aux_path, msg));
+            }
+
+            match aux_crates.get(&aux.name) {
+                Some(existing) if existing.src_path != aux_path => {
+                    let msg = format!(
+                        "aux crate `{}` refers to {}, but another test case declares it as {}",
+                        aux.name,
+                        aux_path.display(),
+                        existing.src_path.display(),
+                    );
+                    return Err(directive_error(msg));
+                }
+                Some(_) => {}
+                None => {
+                    let source = AuxSource {
+                        src_path: aux_path,
+                        proc_macro: aux_directives.proc_macro,
+                    };
+                    aux_crates.insert(aux.name.clone(), source);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run(self, project: &Project) -> TestReport {
+        let start = Instant::now();
+        let show_expected = self.error.is_none() && project.show_expected();
+        message::begin_test(&self.test, show_expected);
+
+        if let Some(error) = self.error {
+            return self.test.report(Err(error), "", start);
+        }
+
+        if let Some(reason) = self.ignore_reason(project) {
+            message::ignored(&reason);
+            return self.test.report(Ok(Outcome::Ignored(reason)), "", start);
+        }
+
+        if project.is_cached(&self) {
+            return self.test.cached_report(start);
+        }
+
+        match self.test.build(project, &self.name, &self.directives) {
+            Ok((mut this_test, build_stdout)) => {
+                let result = self.check(project, &mut this_test, &build_stdout);
+                let stderr = this_test.stderr.preferred();
+                self.test.report(result, stderr, start)
+            }
+            Err(err) => self.test.report(Err(err), "", start),
+        }
+    }
+}
+
+fn resolve_aux_crates(
+    tests: &mut [ExpandedTest],
+    source_dir: &Directory,
+    source_manifest: &dependencies::Manifest,
+) -> Map<String, AuxSource> {
+    let mut aux_crates = Map::new();
+    for t in tests {
+        if t.error.is_none() {
+            if let Err(err) = t.collect_aux_crates(source_dir, source_manifest, &mut aux_crates) {
+                t.error = Some(err);
+            }
+        }
+    }
+    aux_crates
+}
+
+fn edition_dir(edition: Edition) -> String {
+    format!("edition{}", edition.as_str())
+}
+
+// Same as the root package other than its name, edition and bins, so that it
+// has the same dependencies and features. Only the workspace root's
+// [workspace], [patch] and [replace] apply.
+fn edition_manifest(project: &Project, edition: Edition, bins: &[Bin]) -> Result<toml::Value> {
+    let mut manifest = toml::Value::try_from(&project.manifest)?;
+    let bins: Vec<Bin> = bins
+        .iter()
+        .map(|bin| Bin {
+            name: bin.name.clone(),
+            path: Path::new("..").join(&bin.path),
+        })
+        .collect();
+    if let Some(table) = manifest.as_table_mut() {
+        table.remove("workspace");
+        table.remove("patch");
+        table.remove("replace");
+        table.insert("bin".to_owned(), toml::Value::try_from(bins)?);
+        if let Some(package) = table.get_mut("package").and_then(toml::Value::as_table_mut) {
+            let name = project.package_name(Some(edition));
+            package.insert("name".to_owned(), name.into());
+            package.insert("edition".to_owned(), edition.as_str().into());
+            package.remove("resolver");
+        }
+    }
+    Ok(manifest)
+}
+
+// Aux crates are optional dependencies, each enabled only by a feature named
+// after the test case that declares it, and only while building that test case.
+fn add_aux_crates(
+    manifest: &mut Manifest,
+    tests: &[ExpandedTest],
+    aux_crates: &Map<String, AuxSource>,
+    project_dir: &Directory,
+) {
+    for name in aux_crates.keys() {
+        manifest.dependencies.insert(
+            name.clone(),
+            Dependency {
+                version: None,
+                path: Some(Directory::new(path!(project_dir / "aux" / name))),
+                optional: true,
+                default_features: None,
+                features: Vec::new(),
+                git: None,
+                branch: None,
+                tag: None,
+                rev: None,
+                workspace: false,
+                rest: Map::new(),
+            },
+        );
+    }
+
+    for expanded in tests {
+        if expanded.error.is_none() && !expanded.directives.aux_crates.is_empty() {
+            let enables = expanded
+                .directives
+                .aux_crates
+                .iter()
+                .map(|aux| format!("dep:{}", aux.name))
+                .collect();
+            manifest.features.insert(expanded.name.0.clone(), enables);
         }
     }
 }
@@ -531,7 +1555,7 @@ impl ExpandedTest {
 // filter string will be run.
 #[allow(clippy::needless_collect)] // false positive https://github.com/rust-lang/rust-clippy/issues/5991
 fn filter(tests: &mut Vec<ExpandedTest>) {
-    let filters = env::args_os()
+    let filters = std::env::args_os()
         .flat_map(OsString::into_string)
         .filter_map(|mut arg| {
             const PREFIX: &str = "trybuild=";
@@ -555,17 +1579,18 @@ fn filter(tests: &mut Vec<ExpandedTest>) {
 }
 
 #[derive(Deserialize)]
-struct CargoMessage {
-    #[allow(dead_code)]
-    reason: Reason,
-    target: RustcTarget,
-    message: RustcMessage,
-}
-
-#[derive(Deserialize)]
-enum Reason {
+#[serde(tag = "reason")]
+enum CargoMessage {
     #[serde(rename = "compiler-message")]
-    CompilerMessage,
+    CompilerMessage {
+        target: RustcTarget,
+        message: Diagnostic,
+    },
+    #[serde(rename = "compiler-artifact")]
+    CompilerArtifact {
+        target: RustcTarget,
+        executable: Option<PathBuf>,
+    },
 }
 
 #[derive(Deserialize)]
@@ -573,12 +1598,7 @@ struct RustcTarget {
     src_path: PathBuf,
 }
 
-#[derive(Deserialize)]
-struct RustcMessage {
-    rendered: String,
-    level: String,
-}
-
+#[derive(Default)]
 struct ParsedOutputs {
     stdout: String,
     stderrs: Map<CanonicalPath, Stderr>,
@@ -587,6 +1607,9 @@ struct ParsedOutputs {
 struct Stderr {
     success: bool,
     stderr: Variations,
+    diagnostics: Vec<Diagnostic>,
+    executable: Option<PathBuf>,
+    output: Option<Result<Output>>,
 }
 
 impl Default for Stderr {
@@ -594,10 +1617,25 @@ impl Default for Stderr {
         Stderr {
             success: true,
             stderr: Variations::default(),
+            diagnostics: Vec::new(),
+            executable: None,
+            output: None,
         }
     }
 }
 
+fn context<'a>(project: &'a Project, name: &'a Name, test: &'a Test) -> Context<'a> {
+    Context {
+        krate: &name.0,
+        source_dir: &project.source_dir,
+        workspace: &project.workspace,
+        input_file: &test.path,
+        target_dir: &project.target_dir,
+        path_dependencies: &project.path_dependencies,
+        custom: &project.normalizations,
+    }
+}
+
 fn parse_cargo_json(
     project: &Project,
     stdout: &[u8],
@@ -626,29 +1664,32 @@ fn parse_cargo_json(
             // a short-lived bug.
             continue;
         }
-        if let Ok(de) = serde_json::from_str::<CargoMessage>(message) {
-            if de.message.level != "failure-note" {
-                let src_path = CanonicalPath::new(&de.target.src_path);
+        match serde_json::from_str::<CargoMessage>(message) {
+            Ok(CargoMessage::CompilerMessage { target, message }) => {
+                if message.level == "failure-note" {
+                    continue;
+                }
+                let src_path = CanonicalPath::new(&target.src_path);
                 let Some((name, test)) = path_map.get(&src_path) else {
                     continue;
                 };
                 let entry = map.entry(src_path).or_insert_with(Stderr::default);
-                if de.message.level == "error" {
+                if message.level == "error" {
                     entry.success = false;
                 }
-                let normalized = normalize::diagnostics(
-                    &de.message.rendered,
-                    Context {
-                        krate: &name.0,
-                        source_dir: &project.source_dir,
-                        workspace: &project.workspace,
-                        input_file: &test.path,
-                        target_dir: &project.target_dir,
-                        path_dependencies: &project.path_dependencies,
-                    },
-                );
+                let rendered = message.rendered.as_deref().unwrap_or_default();
+                let normalized = normalize::diagnostics(rendered, context(project, name, test));
                 entry.stderr.concat(&normalized);
+                entry.diagnostics.push(message);
+            }
+            Ok(CargoMessage::CompilerArtifact { target, executable }) => {
+                let src_path = CanonicalPath::new(&target.src_path);
+                if path_map.contains_key(&src_path) {
+                    let entry = map.entry(src_path).or_insert_with(Stderr::default);
+                    entry.executable = executable;
+                }
             }
+            Err(_) => {}
         }
     }
     nonmessage_stdout.push_str(remaining);
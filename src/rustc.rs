@@ -0,0 +1,84 @@
+use std::env;
+use std::fmt::{self, Display};
+use std::process::Command;
+use std::str::FromStr;
+
+// Output of `rustc -vV` for the compiler that cargo uses.
+pub(crate) fn version_verbose() -> Option<String> {
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc).arg("-vV").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+#[derive(Debug)]
+pub(crate) struct Rustc {
+    pub version: Version,
+    pub nightly: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) struct Version {
+    pub minor: u32,
+    pub patch: u32,
+}
+
+// "release: 1.80.0-nightly" from `rustc -vV`.
+pub(crate) fn query() -> Option<Rustc> {
+    let version_verbose = version_verbose()?;
+    let release = version_verbose
+        .lines()
+        .find_map(|line| line.strip_prefix("release: "))?;
+    let (version, channel) = match release.split_once('-') {
+        Some((version, channel)) => (version, Some(channel)),
+        None => (release, None),
+    };
+    Some(Rustc {
+        version: version.parse().ok()?,
+        nightly: channel.is_some_and(|channel| channel == "nightly" || channel == "dev"),
+    })
+}
+
+impl FromStr for Version {
+    type Err = ();
+
+    // "1.78" or "1.78.1"
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut parts = string.split('.');
+        if parts.next() != Some("1") {
+            return Err(());
+        }
+        let minor = parts.next().ok_or(())?.parse().map_err(drop)?;
+        let patch = match parts.next() {
+            Some(patch) => patch.parse().map_err(drop)?,
+            None => 0,
+        };
+        if parts.next().is_some() {
+            return Err(());
+        }
+        Ok(Version { minor, patch })
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "1.{}.{}", self.minor, self.patch)
+    }
+}
+
+#[test]
+fn test_parse_version() {
+    let version = "1.78".parse::<Version>().unwrap();
+    assert_eq!(
+        version,
+        Version {
+            minor: 78,
+            patch: 0
+        }
+    );
+    assert!(version < "1.78.1".parse().unwrap());
+    assert!("1.x".parse::<Version>().is_err());
+    assert!("2.0".parse::<Version>().is_err());
+}
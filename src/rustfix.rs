@@ -0,0 +1,138 @@
+use crate::diagnostic::Diagnostic;
+use crate::path::CanonicalPath;
+use std::path::Path;
+
+// Applies the compiler's machine-applicable suggestions to the source of a test
+// case, like rustc's compiletest does for `//@ run-rustfix` and like `cargo fix`
+// does for a whole crate.
+//
+// Each suggestion replaces one or more byte ranges of the source file, as
+// rustc read it: with any byte order mark removed and CRLF turned into LF. A
+// suggestion that overlaps one already taken is skipped, along with every
+// other part of it. For a test case that is a directory, only suggestions
+// within its main.rs are applied.
+pub(crate) fn apply(source: &str, src_path: &Path, diagnostics: &[Diagnostic]) -> String {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let source = source.replace("\r\n", "\n");
+
+    let src_path = CanonicalPath::new(src_path);
+    let mut suggestions = Vec::new();
+    for diag in diagnostics {
+        collect(&src_path, diag, &mut suggestions);
+    }
+    suggestions.sort();
+    suggestions.dedup();
+
+    let mut replacements: Vec<Replacement> = Vec::new();
+    for suggestion in suggestions {
+        let overlaps = suggestion.iter().any(|part| {
+            part.end > source.len()
+                || !source.is_char_boundary(part.start)
+                || !source.is_char_boundary(part.end)
+                || replacements.iter().any(|taken| {
+                    part.start < taken.end && taken.start < part.end
+                        || part.start == part.end && part.start == taken.start
+                })
+        });
+        if !overlaps {
+            replacements.extend(suggestion);
+        }
+    }
+
+    replacements.sort_by_key(|replacement| replacement.start);
+    let mut fixed = source;
+    for replacement in replacements.iter().rev() {
+        fixed.replace_range(replacement.start..replacement.end, &replacement.text);
+    }
+    fixed
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Replacement {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+// Suggestions live in the child diagnostics, each child holding all the parts
+// of one suggestion.
+fn collect(src_path: &CanonicalPath, diag: &Diagnostic, suggestions: &mut Vec<Vec<Replacement>>) {
+    for child in &diag.children {
+        let mut suggestion = Vec::new();
+        for span in &child.spans {
+            let Some(text) = &span.suggested_replacement else {
+                continue;
+            };
+            if span.suggestion_applicability.as_deref() != Some("MachineApplicable")
+                || CanonicalPath::new(&span.file_name) != *src_path
+            {
+                suggestion.clear();
+                break;
+            }
+            suggestion.push(Replacement {
+                start: span.byte_start,
+                end: span.byte_end,
+                text: text.clone(),
+            });
+        }
+        if !suggestion.is_empty() {
+            suggestions.push(suggestion);
+        }
+        collect(src_path, child, suggestions);
+    }
+}
+
+#[test]
+fn test_apply() {
+    use crate::diagnostic::DiagnosticSpan;
+    use std::path::PathBuf;
+
+    let span = |start, end, replacement: &str, applicability: &str| DiagnosticSpan {
+        file_name: PathBuf::from("tests/ui/fix.rs"),
+        byte_start: start,
+        byte_end: end,
+        line_start: 1,
+        column_start: 1,
+        is_primary: true,
+        label: None,
+        suggested_replacement: Some(replacement.to_owned()),
+        suggestion_applicability: Some(applicability.to_owned()),
+        expansion: None,
+    };
+    let diag = |level: &str, spans| Diagnostic {
+        message: String::new(),
+        code: None,
+        level: level.to_owned(),
+        spans,
+        children: Vec::new(),
+        rendered: None,
+    };
+    let with_children = |children| Diagnostic {
+        children,
+        ..diag("error", Vec::new())
+    };
+
+    let source = "let x = foo(a, b);\n";
+    let diagnostics = [
+        with_children(vec![diag(
+            "help",
+            vec![
+                span(8, 11, "bar", "MachineApplicable"),
+                span(12, 13, "&a", "MachineApplicable"),
+            ],
+        )]),
+        // Overlaps the suggestion above.
+        with_children(vec![diag(
+            "help",
+            vec![span(8, 13, "baz(x", "MachineApplicable")],
+        )]),
+        // Not machine-applicable.
+        with_children(vec![diag("help", vec![span(4, 5, "_x", "MaybeIncorrect")])]),
+        with_children(vec![diag(
+            "help",
+            vec![span(15, 16, "&b", "MachineApplicable")],
+        )]),
+    ];
+    let fixed = apply(source, Path::new("tests/ui/fix.rs"), &diagnostics);
+    assert_eq!(fixed, "let x = bar(&a, &b);\n");
+}
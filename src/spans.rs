@@ -0,0 +1,125 @@
+use crate::diagnostic::{Diagnostic, DiagnosticSpan};
+use crate::normalize::{self, Context};
+use crate::path::CanonicalPath;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+
+// Errors whose primary span does not point at the code responsible for them,
+// typically from a procedural macro that used Span::call_site() or spanned its
+// error over the entire input instead of the offending field.
+pub(crate) struct BadSpan {
+    pub location: String,
+    pub message: String,
+    pub problem: Problem,
+}
+
+#[derive(Copy, Clone)]
+pub(crate) enum Problem {
+    OutsideInput,
+    MacroInvocation,
+    WholeItem,
+}
+
+// The input is the test case, either a file or a directory.
+pub(crate) fn check(input: &Path, diagnostics: &[Diagnostic], context: Context) -> Vec<BadSpan> {
+    let input = CanonicalPath::new(input);
+    let mut bad_spans = Vec::new();
+    for diag in diagnostics {
+        if diag.level != "error" {
+            continue;
+        }
+        let Some(span) = diag.primary_span() else {
+            continue;
+        };
+        let problem = if !CanonicalPath::new(&span.file_name).starts_with(&input) {
+            Problem::OutsideInput
+        } else if is_whole_invocation(span) {
+            Problem::MacroInvocation
+        } else if snippet(span).is_some_and(|snippet| is_whole_item(&snippet)) {
+            Problem::WholeItem
+        } else {
+            continue;
+        };
+        bad_spans.push(BadSpan {
+            location: normalize::location(
+                &span.file_name,
+                span.line_start,
+                span.column_start,
+                context,
+            ),
+            message: diag.message.clone(),
+            problem,
+        });
+    }
+    bad_spans
+}
+
+// Spans that come out of a macro expansion, such as from Span::call_site(), are
+// reported at the outermost macro invocation. Spans into the definition of a
+// macro_rules! macro, or of tokens from the macro's input, are not.
+fn is_whole_invocation(span: &DiagnosticSpan) -> bool {
+    let Some(mut expansion) = span.expansion.as_deref() else {
+        return false;
+    };
+    while let Some(outer) = expansion.span.expansion.as_deref() {
+        expansion = outer;
+    }
+    let invocation = &expansion.span;
+    span.file_name == invocation.file_name
+        && span.byte_start == invocation.byte_start
+        && span.byte_end == invocation.byte_end
+}
+
+fn snippet(span: &DiagnosticSpan) -> Option<String> {
+    let source = fs::read_to_string(&span.file_name).ok()?;
+    // Byte offsets are into the source as rustc read it.
+    let source = source.strip_prefix('\u{feff}').unwrap_or(&source);
+    let source = source.replace("\r\n", "\n");
+    source
+        .get(span.byte_start..span.byte_end)
+        .map(str::to_owned)
+}
+
+// Whether the snippet starts with the attributes or keyword of an item and
+// ends with its closing brace or semicolon, like the span of a DeriveInput.
+fn is_whole_item(snippet: &str) -> bool {
+    let snippet = snippet.trim();
+    if !snippet.ends_with('}') && !snippet.ends_with(';') {
+        return false;
+    }
+    if snippet.starts_with("#[") {
+        return true;
+    }
+    let mut words = snippet
+        .split(|ch: char| !ch.is_ascii_alphanumeric() && ch != '_')
+        .filter(|word| !word.is_empty())
+        .skip_while(|word| ["pub", "crate", "super", "self", "in"].contains(word));
+    words.next().is_some_and(|word| {
+        [
+            "struct", "enum", "union", "fn", "impl", "trait", "mod", "type", "const", "static",
+            "unsafe", "async", "extern",
+        ]
+        .contains(&word)
+    })
+}
+
+impl Display for Problem {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(match self {
+            Problem::OutsideInput => "points outside of the test case",
+            Problem::MacroInvocation => "points at the macro invocation, as with Span::call_site()",
+            Problem::WholeItem => "covers the whole item",
+        })
+    }
+}
+
+#[test]
+fn test_is_whole_item() {
+    assert!(is_whole_item("struct Unit;"));
+    assert!(is_whole_item("pub(crate) struct S {\n    field: u8,\n}"));
+    assert!(is_whole_item("#[repr(C)]\nenum E {\n    A,\n}"));
+    assert!(!is_whole_item("field: u8"));
+    assert!(!is_whole_item("Vec<u8>"));
+    assert!(!is_whole_item("{ field: u8 }"));
+}
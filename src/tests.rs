@@ -19,6 +19,7 @@ macro_rules! test_normalize {
                     name: String::from("diesel"),
                     normalized_path: crate::directory::Directory::new("/home/user/documents/rust/diesel/diesel"),
                 }],
+                custom: &[],
             };
             let original = $original;
             let variations = crate::normalize::diagnostics(original, context);
@@ -0,0 +1,31 @@
+test_normalize! {
+    INPUT="tests/ui/dir_case"
+"
+error[E0308]: mismatched types
+ --> /git/trybuild/test_suite/tests/ui/dir_case/helper.rs:2:17
+  |
+2 |     let _: u8 = helper();
+  |            --   ^^^^^^^^ expected `u8`, found `&str`
+  |            |
+  |            expected due to this
+  |
+note: return type inferred to be `&str` here
+ --> /git/trybuild/test_suite/tests/ui/dir_case_support.rs:1:20
+  |
+1 | pub fn helper() -> &'static str {
+  |                    ^^^^^^^^^^^^
+" "
+error[E0308]: mismatched types
+ --> tests/ui/dir_case/helper.rs:2:17
+  |
+2 |     let _: u8 = helper();
+  |            --   ^^^^^^^^ expected `u8`, found `&str`
+  |            |
+  |            expected due to this
+  |
+note: return type inferred to be `&str` here
+ --> tests/ui/dir_case_support.rs
+  |
+  | pub fn helper() -> &'static str {
+  |                    ^^^^^^^^^^^^
+"}
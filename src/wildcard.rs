@@ -0,0 +1,226 @@
+use std::cmp;
+
+// Wildcards in *.stderr snapshots, with the same syntax as in the tests of
+// Cargo itself:
+//
+//     error[E0277]: the trait bound `Thing: Trait` is not satisfied
+//      --> tests/ui/trait.rs:[..]
+//     ...
+//     note: required by a bound in `assert_trait`
+//
+// A line consisting of just `...` matches any number of lines, including none,
+// and `[..]` within a line matches any part of that line.
+
+const ANY_LINES: &str = "...";
+const ANY: &str = "[..]";
+
+pub(crate) fn has_wildcards(expected: &str) -> bool {
+    expected
+        .lines()
+        .any(|line| line == ANY_LINES || line.contains(ANY))
+}
+
+pub(crate) fn matches(expected: &str, actual: &str) -> bool {
+    if expected == actual {
+        return true;
+    }
+    if !has_wildcards(expected) {
+        return false;
+    }
+
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // Whether the snapshot lines so far can match the first j actual lines.
+    let mut reachable = vec![false; actual.len() + 1];
+    reachable[0] = true;
+    for pattern in expected.lines() {
+        if pattern == ANY_LINES {
+            for j in 1..reachable.len() {
+                reachable[j] |= reachable[j - 1];
+            }
+        } else {
+            for j in (1..reachable.len()).rev() {
+                reachable[j] = reachable[j - 1] && line_matches(pattern, actual[j - 1]);
+            }
+            reachable[0] = false;
+        }
+    }
+    reachable[actual.len()]
+}
+
+fn line_matches(pattern: &str, line: &str) -> bool {
+    if !pattern.contains(ANY) {
+        return pattern == line;
+    }
+
+    let mut parts: Vec<&str> = pattern.split(ANY).collect();
+    let last = parts.pop().unwrap();
+    let Some(mut rest) = line.strip_prefix(parts[0]) else {
+        return false;
+    };
+    for part in &parts[1..] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+// The new contents of a snapshot that no longer matches, for TRYBUILD=overwrite.
+// Lines of the old snapshot that still match something are kept, wildcards and
+// all, while the rest of the actual output is written out literally.
+//
+// This finds the cheapest alignment of the old snapshot against the actual
+// output in which every `...` line absorbs whatever it can for free. Replacing
+// a line with a new one costs less than deleting it, so that a line which
+// changed right next to a `...` shows up in the snapshot as the new line,
+// instead of disappearing into the wildcard. Inserting a line costs less than
+// replacing one, so that old lines stay lined up with the lines they match.
+pub(crate) fn overwrite(expected: &str, actual: &str) -> String {
+    if !has_wildcards(expected) {
+        return actual.to_owned();
+    }
+
+    const INSERT: u32 = 1;
+    const REPLACE: u32 = 2;
+    const DELETE: u32 = 3;
+
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let (n, m) = (expected.len(), actual.len());
+
+    // cost[i][j] is the cost of aligning expected[i..] against actual[j..].
+    let mut cost = vec![vec![0; m + 1]; n + 1];
+    for i in (0..=n).rev() {
+        for j in (0..=m).rev() {
+            cost[i][j] = if i == n {
+                (m - j) as u32 * INSERT
+            } else if expected[i] == ANY_LINES {
+                let finish = cost[i + 1][j];
+                if j == m {
+                    finish
+                } else {
+                    cmp::min(cost[i][j + 1], finish)
+                }
+            } else if j == m {
+                DELETE + cost[i + 1][j]
+            } else {
+                let mut best = cmp::min(
+                    REPLACE + cost[i + 1][j + 1],
+                    cmp::min(DELETE + cost[i + 1][j], INSERT + cost[i][j + 1]),
+                );
+                if line_matches(expected[i], actual[j]) {
+                    best = cmp::min(best, cost[i + 1][j + 1]);
+                }
+                best
+            };
+        }
+    }
+
+    let mut blessed = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        let line = if i == n {
+            j += 1;
+            actual[j - 1]
+        } else if expected[i] == ANY_LINES {
+            if j < m && cost[i][j + 1] == cost[i][j] {
+                j += 1;
+                continue;
+            }
+            i += 1;
+            ANY_LINES
+        } else if j < m && line_matches(expected[i], actual[j]) && cost[i + 1][j + 1] == cost[i][j]
+        {
+            i += 1;
+            j += 1;
+            expected[i - 1]
+        } else if j < m && REPLACE + cost[i + 1][j + 1] == cost[i][j] {
+            i += 1;
+            j += 1;
+            actual[j - 1]
+        } else if DELETE + cost[i + 1][j] == cost[i][j] {
+            i += 1;
+            continue;
+        } else {
+            j += 1;
+            actual[j - 1]
+        };
+        blessed.push_str(line);
+        blessed.push('\n');
+    }
+    blessed
+}
+
+#[test]
+fn test_matches() {
+    let actual = "\
+        error[E0277]: the trait bound `Thing: Trait` is not satisfied\n\
+        \x20--> tests/ui/trait.rs:8:18\n\
+        help: the following other types implement trait `Trait`:\n\
+        \x20 i32\n\
+        \x20 u8\n\
+        note: required by a bound in `assert_trait`\n\
+    ";
+    assert!(matches(actual, actual));
+
+    let expected = "\
+        error[E0277]: the trait bound `Thing: Trait` is not satisfied\n\
+        \x20--> tests/ui/trait.rs:[..]\n\
+        ...\n\
+        note: required by a bound in `[..]`\n\
+    ";
+    assert!(matches(expected, actual));
+
+    let expected = "\
+        error[E0277]: [..] is not satisfied\n\
+        ...\n\
+        ...\n\
+    ";
+    assert!(matches(expected, actual));
+
+    let expected = "\
+        error[E0277]: the trait bound `Thing: Trait` is not satisfied\n\
+        ...\n\
+        note: required by a bound in `assert_other`\n\
+    ";
+    assert!(!matches(expected, actual));
+
+    assert!(line_matches("a[..]b[..]c", "abc"));
+    assert!(line_matches("a[..]b[..]c", "axxbxxc"));
+    assert!(!line_matches("a[..]b[..]c", "axxc"));
+    assert!(!line_matches("ab[..]ba", "aba"));
+}
+
+#[test]
+fn test_overwrite() {
+    let expected = "\
+        error: first\n\
+        ...\n\
+        note: old[..]\n\
+        error: second [..]\n\
+    ";
+    let actual = "\
+        error: first\n\
+        \x20 list\n\
+        \x20 of things\n\
+        note: new note\n\
+        error: second message\n\
+        added\n\
+    ";
+    let blessed = overwrite(expected, actual);
+    assert_eq!(
+        blessed,
+        "\
+        error: first\n\
+        ...\n\
+        note: new note\n\
+        error: second [..]\n\
+        added\n\
+        ",
+    );
+    assert!(matches(&blessed, actual));
+
+    assert_eq!(overwrite("no wildcards\n", actual), actual);
+}
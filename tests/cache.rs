@@ -0,0 +1,48 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// In a test target of its own, as the cache is turned on for the whole process.
+#[test]
+fn cache() {
+    env::set_var("TRYBUILD_CACHE", "1");
+
+    let tmp = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let _ = fs::remove_file(tmp.join("../tests/trybuild/trybuild/cache.json"));
+    let dir = tmp.join("cache");
+    fs::create_dir_all(&dir).unwrap();
+    let main = dir.join("main.rs");
+    let module = dir.join("module.rs");
+    fs::write(
+        &main,
+        "#[path = \"module.rs\"]\nmod module;\n\nfn main() {\n    module::f();\n}\n",
+    )
+    .unwrap();
+    fs::write(&module, "pub fn f() {}\n").unwrap();
+
+    let run = || {
+        let t = trybuild::TestCases::new();
+        t.compile_pass(&main);
+        // Built by itself, after the other test case.
+        t.compile_pass("tests/ui/directive-edition.rs");
+        t.run().unwrap()
+    };
+
+    let first = run();
+    for test in &first.tests {
+        assert!(matches!(test.outcome, trybuild::Outcome::Passed));
+        assert!(!test.cached);
+    }
+
+    let second = run();
+    for test in &second.tests {
+        assert!(matches!(test.outcome, trybuild::Outcome::Passed));
+        assert!(test.cached, "{} was built again", test.path.display());
+    }
+
+    fs::write(&module, "pub fn f() {\n    let _ = 1;\n}\n").unwrap();
+    let third = run();
+    assert!(matches!(third.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(!third.tests[0].cached);
+    assert!(third.tests[1].cached);
+}
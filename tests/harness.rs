@@ -0,0 +1,6 @@
+fn main() {
+    trybuild::harness(|t| {
+        t.pass("tests/ui/run-pass-0.rs");
+        t.compile_fail("tests/ui/compile-fail-2.rs");
+    });
+}
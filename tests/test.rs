@@ -20,3 +20,295 @@ fn test() {
     t.compile_fail("tests/ui/compile-fail-2.rs");
     t.compile_fail("tests/ui/compile-fail-3.rs");
 }
+
+#[test]
+fn snapshot_output() {
+    let t = trybuild::TestCases::new();
+    t.snapshot_output();
+    t.pass("tests/ui/print-both.rs");
+    t.pass("tests/ui/print-stdout.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    // No print-stdout.stdout to compare against.
+    assert!(matches!(
+        report.tests[1].outcome,
+        trybuild::Outcome::CreatedWip
+    ));
+}
+
+#[test]
+fn snapshot_warnings() {
+    let t = trybuild::TestCases::new();
+    t.snapshot_warnings();
+    t.pass("tests/ui/pass-warning.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(report.tests[0].stderr.contains("unused variable: `unused`"));
+}
+
+#[test]
+fn deny_warnings() {
+    let t = trybuild::TestCases::new();
+    t.deny_warnings();
+    t.pass("tests/ui/pass-warning.rs");
+    t.pass("tests/ui/run-pass-1.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(
+        report.tests[0].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::Warnings),
+    ));
+    assert!(matches!(report.tests[1].outcome, trybuild::Outcome::Passed));
+}
+
+#[test]
+fn normalize() {
+    let t = trybuild::TestCases::new();
+    t.normalize("0x5f3a91", "$HASH");
+    t.compile_fail("tests/ui/compile-fail-normalize.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(report.tests[0].stderr.contains("__Hidden_$HASH"));
+    assert!(!report.tests[0].stderr.contains("0x5f3a91"));
+}
+
+#[test]
+fn normalize_with() {
+    fn assert_send<T: Send>(_: &T) {}
+
+    let t = trybuild::TestCases::new();
+    t.normalize_with(|output| output.replace("0x5f3a91", "$HASH"));
+    assert_send(&t);
+    t.compile_fail("tests/ui/compile-fail-normalize.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+}
+
+#[test]
+fn batch() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/run-pass-0.rs");
+    t.pass("tests/ui/run-fail.rs");
+    t.pass("tests/ui/dylib-path.rs");
+    t.pass("tests/ui/run-pass-1.rs");
+    let report = t.run().unwrap();
+    assert_eq!(report.tests.len(), 4);
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(
+        report.tests[1].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::RunFailed),
+    ));
+    assert!(matches!(report.tests[2].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(report.tests[3].outcome, trybuild::Outcome::Passed));
+    assert_eq!(report.failures(), 1);
+}
+
+#[test]
+fn report() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/run-pass-0.rs");
+    t.compile_fail("tests/ui/run-pass-3.rs");
+    let report = t.run().unwrap();
+    assert_eq!(report.tests.len(), 2);
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(
+        report.tests[1].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::ShouldNotHaveCompiled),
+    ));
+    assert_eq!(report.failures(), 1);
+}
+
+#[test]
+fn timeout() {
+    let t = trybuild::TestCases::new();
+    t.timeout(std::time::Duration::from_secs(3));
+    t.pass("tests/ui/run-forever.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(
+        &report.tests[0].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::Timeout(path, _))
+            if path.ends_with("run-forever.rs"),
+    ));
+}
+
+#[test]
+fn directory() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/multi-file/");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+}
+
+#[test]
+fn aux_crate() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/aux-crate.rs");
+    t.compile_fail("tests/ui/aux-crate-non-exhaustive.rs");
+    // The aux crate declared by the other test cases is not visible here.
+    t.compile_fail("tests/ui/aux-crate-undeclared.rs");
+    let report = t.run().unwrap();
+    for test in &report.tests {
+        assert!(matches!(test.outcome, trybuild::Outcome::Passed));
+    }
+}
+
+#[test]
+fn directives() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/directives.rs");
+    t.pass("tests/ui/directive-edition.rs");
+    t.pass("tests/ui/directive-ignore.rs");
+    t.pass("tests/ui/directive-min-rustc.rs");
+    let report = t.run().unwrap();
+    assert_eq!(report.failures(), 0);
+    assert!(matches!(
+        &report.tests[2].outcome,
+        trybuild::Outcome::Ignored(reason) if reason == "does not compile",
+    ));
+    assert!(matches!(
+        &report.tests[3].outcome,
+        trybuild::Outcome::Ignored(reason) if reason == "requires rustc 1.999.0 or newer",
+    ));
+}
+
+#[test]
+fn expand() {
+    let t = trybuild::TestCases::new();
+    t.expand("tests/ui/expand.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+}
+
+#[test]
+fn json_diagnostics() {
+    let t = trybuild::TestCases::new();
+    t.json_diagnostics();
+    t.compile_fail("tests/ui/json-diagnostics.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+}
+
+#[test]
+fn rustfix() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/rustfix.rs");
+    let report = t.run().unwrap();
+    assert_eq!(report.failures(), 0);
+}
+
+#[test]
+fn check_spans() {
+    let t = trybuild::TestCases::new();
+    t.check_spans();
+    t.compile_fail("tests/ui/spans.rs");
+    t.compile_fail("tests/ui/spans-bad.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(
+        report.tests[1].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::BadSpans),
+    ));
+}
+
+#[test]
+fn check_spans_macro_rules() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/spans-macro-rules.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+}
+
+#[test]
+fn compile_fail_build() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail_build("tests/ui/post-mono.rs");
+    t.compile_fail("tests/ui/post-mono-checked.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    // Only type-checked, which does not get as far as the error.
+    assert!(matches!(
+        report.tests[1].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::ShouldNotHaveCompiled),
+    ));
+}
+
+#[test]
+fn compile_fail_with_codes() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail_with_codes("tests/ui/compile-fail-codes.rs", ["E0425", "E0599"]);
+    let codes: &[&str] = &["E0425"];
+    t.compile_fail_with_codes("tests/ui/compile-fail-codes-uncoded.rs", codes);
+    t.compile_fail_with_directive_codes("tests/ui/compile-fail-codes-directive.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(
+        report.tests[1].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::Mismatch),
+    ));
+    assert!(matches!(report.tests[2].outcome, trybuild::Outcome::Passed));
+}
+
+#[test]
+fn compile_pass() {
+    let t = trybuild::TestCases::new();
+    t.compile_pass("tests/ui/compile-pass.rs");
+    t.compile_fail("tests/ui/compile-fail-2.rs");
+    t.compile_pass("tests/ui/compile-fail-0.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(report.tests[1].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(
+        report.tests[2].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::CargoFail),
+    ));
+}
+
+#[test]
+fn compile_fail_annotated() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail_annotated("tests/ui/compile-fail-annotated.rs");
+    // The second error has no `//~` annotation.
+    t.compile_fail_annotated("tests/ui/compile-fail-unannotated.rs");
+    // An error in a module other than main.rs.
+    t.compile_fail_annotated("tests/ui/multi-file-annotated/");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(
+        report.tests[1].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::Mismatch),
+    ));
+    assert!(matches!(report.tests[2].outcome, trybuild::Outcome::Passed));
+}
+
+#[test]
+fn run_fail() {
+    let t = trybuild::TestCases::new();
+    t.run_fail("tests/ui/run-fail-panic.rs");
+    t.run_fail_with_code("tests/ui/run-fail-code.rs", 2);
+    t.run_fail_with_code("tests/ui/run-fail-code.rs", 3);
+    t.run_fail("tests/ui/run-pass-0.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(report.tests[1].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(
+        report.tests[2].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::ExitCode(3, Some(2))),
+    ));
+    assert!(matches!(
+        report.tests[3].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::RunSucceeded),
+    ));
+}
+
+#[test]
+fn wildcard() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/wildcard.rs");
+    // The `[..]` line names a different method.
+    t.compile_fail("tests/ui/wildcard-mismatch.rs");
+    let report = t.run().unwrap();
+    assert!(matches!(report.tests[0].outcome, trybuild::Outcome::Passed));
+    assert!(matches!(
+        report.tests[1].outcome,
+        trybuild::Outcome::Failed(trybuild::Error::Mismatch),
+    ));
+}
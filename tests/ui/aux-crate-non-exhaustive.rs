@@ -0,0 +1,5 @@
+//@ aux-crate: helper=aux/helper.rs
+
+fn main() {
+    let _ = helper::Config { verbose: true };
+}
@@ -0,0 +1,9 @@
+//@ aux-crate: helper=aux/helper.rs
+//@ aux-crate: derive=aux/derive.rs
+
+#[derive(derive::Hello)]
+struct Unit;
+
+fn main() {
+    assert_eq!(helper::greeting(), Unit::hello());
+}
@@ -0,0 +1,17 @@
+//@ proc-macro
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+
+#[proc_macro_derive(Hello)]
+pub fn derive_hello(input: TokenStream) -> TokenStream {
+    let name = input
+        .into_iter()
+        .skip_while(|tt| tt.to_string() != "struct")
+        .nth(1)
+        .unwrap();
+    format!("impl {} {{ fn hello() -> &'static str {{ \"hello\" }} }}", name)
+        .parse()
+        .unwrap()
+}
@@ -0,0 +1,8 @@
+#[non_exhaustive]
+pub struct Config {
+    pub verbose: bool,
+}
+
+pub fn greeting() -> &'static str {
+    "hello"
+}
@@ -0,0 +1,47 @@
+//@ proc-macro
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+// compile_error!(message), with the first token spanned at `start` and the
+// rest at `end`, the way syn::Error::new_spanned covers a range of tokens.
+fn compile_error(message: &str, start: Span, end: Span) -> TokenStream {
+    let mut bang = Punct::new('!', Spacing::Alone);
+    bang.set_span(end);
+    let mut literal = Literal::string(message);
+    literal.set_span(end);
+    let mut group = Group::new(Delimiter::Parenthesis, TokenTree::from(literal).into());
+    group.set_span(end);
+    let mut semi = Punct::new(';', Spacing::Alone);
+    semi.set_span(end);
+    TokenStream::from_iter([
+        TokenTree::from(Ident::new("compile_error", start)),
+        bang.into(),
+        group.into(),
+        semi.into(),
+    ])
+}
+
+#[proc_macro_derive(CallSite)]
+pub fn derive_call_site(_input: TokenStream) -> TokenStream {
+    let span = Span::call_site();
+    compile_error("unsupported field", span, span)
+}
+
+#[proc_macro_derive(FieldSpan)]
+pub fn derive_field_span(input: TokenStream) -> TokenStream {
+    let Some(TokenTree::Group(fields)) = input.into_iter().last() else {
+        unreachable!();
+    };
+    let field = fields.stream().into_iter().next().unwrap().span();
+    compile_error("unsupported field", field, field)
+}
+
+#[proc_macro_derive(WholeItem)]
+pub fn derive_whole_item(input: TokenStream) -> TokenStream {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let start = tokens.first().unwrap().span();
+    let end = tokens.last().unwrap().span();
+    compile_error("unsupported field", start, end)
+}
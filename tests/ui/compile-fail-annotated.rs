@@ -0,0 +1,3 @@
+fn main() {
+    let _x: u8 = ""; //~ ERROR mismatched types
+}
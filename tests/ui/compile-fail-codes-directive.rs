@@ -0,0 +1,6 @@
+//@ error-codes: E0425, uncoded
+
+fn main() {
+    let _ = undefined;
+    compile_error!("no code");
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let _ = undefined;
+    compile_error!("no code");
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let _ = undefined;
+    0u8.frobnicate();
+}
@@ -0,0 +1,3 @@
+compile_error!("generated type __Hidden_0x5f3a91");
+
+fn main() {}
@@ -0,0 +1,4 @@
+fn main() {
+    let _x: u8 = ""; //~ ERROR mismatched types
+    let _y: bool = 1;
+}
@@ -0,0 +1,3 @@
+fn main() {
+    panic!("compile_pass test cases are never run");
+}
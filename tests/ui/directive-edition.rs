@@ -0,0 +1,7 @@
+//@ edition: 2015
+
+fn main() {
+    // A keyword since 2018.
+    let async = 1;
+    let _ = async;
+}
@@ -0,0 +1,5 @@
+//@ ignore: does not compile
+
+fn main() {
+    let _: u8 = "";
+}
@@ -0,0 +1,5 @@
+//@ min-rustc: 1.999
+
+fn main() {
+    compile_error!("this compiler is too old");
+}
@@ -0,0 +1,15 @@
+//@ compile-flags: --cfg=trybuild_flag
+//@ rustc-env: TRYBUILD_GREETING=hello
+//@ features: diff
+
+#![allow(unexpected_cfgs)]
+
+#[cfg(not(trybuild_flag))]
+compile_error!("compile-flags were not applied");
+
+#[cfg(not(feature = "diff"))]
+compile_error!("features were not applied");
+
+fn main() {
+    assert_eq!(env!("TRYBUILD_GREETING"), "hello");
+}
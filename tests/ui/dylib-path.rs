@@ -0,0 +1,17 @@
+use std::env;
+
+// Dynamic libraries among the dependencies are found in the deps directory
+// next to the executable, as under `cargo run`.
+fn main() {
+    let var = if cfg!(windows) {
+        "PATH"
+    } else if cfg!(target_os = "macos") {
+        "DYLD_FALLBACK_LIBRARY_PATH"
+    } else {
+        "LD_LIBRARY_PATH"
+    };
+    let paths = env::var_os(var).unwrap();
+    let exe = env::current_exe().unwrap();
+    let deps_dir = exe.parent().unwrap().join("deps");
+    assert!(env::split_paths(&paths).any(|path| path == deps_dir));
+}
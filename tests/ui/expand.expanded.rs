@@ -0,0 +1,7 @@
+#![feature(prelude_import)]
+extern crate std;
+#[prelude_import]
+use std::prelude::rust_2021::*;
+macro_rules! square { ($e:expr) => { $e * $e }; }
+
+fn main() { let _ = (2 + 1) * (2 + 1); }
@@ -0,0 +1,9 @@
+macro_rules! square {
+    ($e:expr) => {
+        $e * $e
+    };
+}
+
+fn main() {
+    let _ = square!(2 + 1);
+}
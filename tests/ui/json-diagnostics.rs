@@ -0,0 +1,6 @@
+use std::rc::Rc;
+
+fn main() {
+    let rc = Rc::new(0);
+    std::thread::spawn(move || drop(rc));
+}
@@ -0,0 +1,6 @@
+mod helper;
+
+fn main() {
+    helper::f();
+    let _: bool = 1; //~ ERROR mismatched types
+}
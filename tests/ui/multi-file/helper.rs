@@ -0,0 +1,3 @@
+pub fn f() {
+    let _: u8 = "not a number";
+}
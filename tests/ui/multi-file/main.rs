@@ -0,0 +1,9 @@
+mod helper;
+
+#[path = "other/util.rs"]
+mod util;
+
+fn main() {
+    helper::f();
+    util::g();
+}
@@ -0,0 +1,3 @@
+pub fn g() {
+    let _: bool = 1;
+}
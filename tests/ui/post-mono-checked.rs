@@ -0,0 +1,13 @@
+struct NonZeroSized<T>(T);
+
+impl<T> NonZeroSized<T> {
+    const CHECK: () = assert!(std::mem::size_of::<T>() != 0, "zero-sized type");
+}
+
+fn require_non_zero_sized<T>() {
+    let () = NonZeroSized::<T>::CHECK;
+}
+
+fn main() {
+    require_non_zero_sized::<()>();
+}
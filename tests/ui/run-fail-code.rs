@@ -0,0 +1,6 @@
+use std::process;
+
+fn main() {
+    eprintln!("exiting with code 2");
+    process::exit(2);
+}
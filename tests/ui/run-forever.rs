@@ -0,0 +1,8 @@
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    loop {
+        thread::sleep(Duration::from_secs(1));
+    }
+}
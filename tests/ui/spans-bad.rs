@@ -0,0 +1,13 @@
+//@ aux-crate: spans=aux/spans.rs
+
+#[derive(spans::CallSite)]
+struct CallSite {
+    field: u8,
+}
+
+#[derive(spans::WholeItem)]
+struct WholeItem {
+    field: u8,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+//@ check-spans
+
+macro_rules! reject {
+    ($ty:ident) => {
+        compile_error!(concat!("unsupported type ", stringify!($ty)));
+    };
+}
+
+reject!(Unsupported);
+
+fn main() {}
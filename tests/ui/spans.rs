@@ -0,0 +1,8 @@
+//@ aux-crate: spans=aux/spans.rs
+
+#[derive(spans::FieldSpan)]
+struct FieldSpan {
+    field: u8,
+}
+
+fn main() {}
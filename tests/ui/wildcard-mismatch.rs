@@ -0,0 +1,5 @@
+struct Thing;
+
+fn main() {
+    let _ = Thing.add(1);
+}
@@ -0,0 +1,8 @@
+use std::ops::Add;
+
+struct Thing;
+
+fn main() {
+    let _ = 1 + Thing;
+    let _ = Thing.add(1);
+}